@@ -14,6 +14,14 @@ impl Age {
         }
     }
 
+    /// An age that began `days` of simulated time ago.
+    pub fn from_days(time_controller: &TimeController, days: f64) -> Self {
+        Self {
+            simulated_birth_timestamp: time_controller.simulated_elapsed_secs()
+                - days * 24. * 60. * 60.,
+        }
+    }
+
     pub fn age_seconds(&self, time_controller: &TimeController) -> f64 {
         (time_controller.simulated_elapsed_secs() - self.simulated_birth_timestamp).max(0.0)
     }
@@ -1,20 +1,30 @@
 use avian3d::prelude::*;
 use bevy::prelude::*;
-use big_brain::{
-    prelude::{FirstToScore, Steps},
-    thinker::Thinker,
-};
 use rand::Rng;
 
 use crate::{
     age::Age,
     asset_loader::{AnimationData, SceneAssets, asset_load_handle},
+    avoidance::AvoidanceAgent,
+    bounds::{Bounded, WorldBounds},
+    controller::CharacterController,
     hud::pick_guy,
+    interpolation::Interpolated,
+    life_cycle::{LifeCycle, LifeStageChangedEvent},
     movement::{Destination, Speed, animate_movement, idle_on_stop, on_arrive},
-    navigation::{EntityPath, FindPathEvent, NoPathFoundEvent, Obstacle},
-    needs::{Eat, Hungry, MoveToNearest, PhysicalNeeds},
+    navigation::{EntityPath, FindPathEvent, Obstacle, return_to_navmesh},
+    needs::{BrowseHeight, PhysicalNeeds, Satiety},
+    personality::Personality,
+    placement::{Placement, PlacementConstraints, PlacementError, PlacementKind, SearchArea},
+    predation::{Predator, Prey, become_carcass},
+    species::Diet,
     time_control::TimeController,
-    vegetation::{Fruit, Tree, spawn_trees, within_dist_sqrd_of_transforms},
+    vegetation::spawn_trees,
+};
+
+const ANIMAL_CONFIG: AnimalConfig = AnimalConfig {
+    spawn_margin: 15.,
+    wander_margin: 5.,
 };
 
 pub struct AnimalPlugin;
@@ -32,69 +42,58 @@ impl Plugin for AnimalPlugin {
     }
 }
 
-static ANIMAL_CONFIG: AnimalConfig = AnimalConfig { initial_count: 10 };
-
 #[derive(Component, Debug)]
-#[require(PhysicalNeeds)]
+#[require(PhysicalNeeds, Bounded, Interpolated)]
 pub struct Animal {
     animal_type: AnimalType,
 }
 
-#[derive(Clone, Debug)]
-enum AnimalType {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimalType {
     Deer,
+    Wolf,
 }
 
-#[derive(Event, Debug)]
+#[derive(Event, Clone, Debug)]
 struct SpawnAnimalEvent {
     animal_type: AnimalType,
-    translation: Option<Vec3>,
+    age_days: f64,
 }
 
 impl SpawnAnimalEvent {
-    fn new(animal_type: AnimalType, translation: Option<Vec3>) -> Self {
+    fn new(animal_type: AnimalType, age_days: f64) -> Self {
         Self {
             animal_type,
-            translation,
+            age_days,
         }
     }
 }
 
 fn spawn_animals(mut spawn_animal_event_writer: EventWriter<SpawnAnimalEvent>) {
-    for _ in 0..ANIMAL_CONFIG.initial_count {
-        spawn_animal_event_writer.send(SpawnAnimalEvent::new(AnimalType::Deer, None));
+    let mut rng = rand::rng();
+    for animal_type in AnimalType::ALL {
+        let species = animal_type.species();
+        // Start off with a population of adults.
+        let adult_ages = species.life_cycle.adult_days..species.life_cycle.senescent_days;
+        for _ in 0..species.initial_count {
+            spawn_animal_event_writer.send(SpawnAnimalEvent::new(
+                animal_type,
+                rng.random_range(adult_ages.clone()) as f64,
+            ));
+        }
     }
 }
 
-fn unstuck_animals(
-    trigger: Trigger<NoPathFoundEvent>,
-    mut commands: Commands,
-    query: Query<&Transform, (With<Animal>, Without<Destination>)>,
-    obstacle_query: Query<&Transform, With<Tree>>,
+fn apply_life_stage(
+    trigger: Trigger<LifeStageChangedEvent>,
+    mut query: Query<(&Animal, &mut Transform, &mut Speed, &mut Satiety)>,
 ) {
-    let standoff_dist = 1.5;
-    if let Ok(animal_transform) = query.get(trigger.entity()) {
-        if let Some(obstacle_translation) = within_dist_sqrd_of_transforms(
-            0.5,
-            obstacle_query.iter(),
-            &animal_transform.translation,
-        ) {
-            let mut dir = animal_transform.translation - obstacle_translation;
-            dir.y = 0.0;
-
-            if dir.length_squared() > 0.0 {
-                println!("Unstucking: {:?}", trigger.entity());
-                let flee_dir = dir.normalize(); // unit vector away
-                // multiply by your standoff distance
-                let mut target = animal_transform.translation + flee_dir * standoff_dist;
-                target.y = animal_transform.translation.y;
-                commands
-                    .entity(trigger.entity())
-                    .insert(Destination::new(target));
-            } else {
-                println!("NONONO");
-            }
-        }
+    if let Ok((animal, mut transform, mut speed, mut satiety)) = query.get_mut(trigger.entity()) {
+        let species = animal.animal_type.species();
+        let modifiers = species.stage_modifiers.get(trigger.event().to);
+        transform.scale = Vec3::splat(species.scale * modifiers.scale);
+        *speed = species.speed.scaled(modifiers.speed);
+        satiety.drain_speed_per_sec = Satiety::default().drain_speed_per_sec * modifiers.need_rate;
     }
 }
 
@@ -102,15 +101,12 @@ fn goto_random(
     // mut commands: Commands,
     query: Query<(Entity, &Transform), (With<Animal>, (Without<Destination>, Without<EntityPath>))>,
     obstacle_query: Query<&Transform, With<Obstacle>>,
+    bounds: Res<WorldBounds>,
     mut find_path_event_writer: EventWriter<FindPathEvent>,
 ) {
     let mut rng = rand::rng();
     for (entity, transform) in query.iter() {
-        let to_point = Vec3::new(
-            rng.random_range(-20.0..20.0),
-            0.0,
-            rng.random_range(-20.0..20.0),
-        );
+        let to_point = bounds.random_point(&mut rng, ANIMAL_CONFIG.wander_margin);
 
         // let from_point = Vec2::new(transform.translation.x, transform.translation.z);
         find_path_event_writer.send(FindPathEvent::new(transform.translation, to_point, entity));
@@ -127,88 +123,117 @@ fn spawn_animal(
     mut commands: Commands,
     scene_assets: Res<SceneAssets>,
     time_controller: Res<TimeController>,
+    mut placement: Placement,
     mut spawn_animal_event_reader: EventReader<SpawnAnimalEvent>,
+    mut waiting_for_navmesh: Local<Vec<SpawnAnimalEvent>>,
     // mut players: Query<&mut AnimationPlayer>,
 ) {
     let mut rng = rand::rng();
 
-    for spawn_event in spawn_animal_event_reader.read() {
+    let spawn_events: Vec<SpawnAnimalEvent> = waiting_for_navmesh
+        .drain(..)
+        .chain(spawn_animal_event_reader.read().cloned())
+        .collect();
+
+    for spawn_event in spawn_events {
         let random_angle = rng.random_range(0.0..std::f32::consts::PI);
         let rotation: Quat = Quat::from_axis_angle(Vec3::new(0., 1., 0.), random_angle);
 
-        let translation = spawn_event.translation;
-        let translation = translation.unwrap_or(Vec3::new(
-            rng.random_range(-10.0..10.),
-            0.,
-            rng.random_range(-10.0..10.),
-        ));
-
-        let name;
-        let asset;
-        let speed;
-        let animation_index;
-        let collider_stuff;
-        match spawn_event.animal_type {
-            AnimalType::Deer => {
-                if rng.random_bool(0.5) {
-                    name = "deer";
-                    asset = scene_assets.deer.clone();
-                    animation_index = 0;
-                    collider_stuff = (
-                        Collider::cuboid(0.25, 1.0, 1.1),
-                        Transform::from_translation(Vec3::new(0.0, 2.25, 0.5)),
-                    );
-                } else {
-                    name = "stag";
-                    asset = scene_assets.stag.clone();
-                    animation_index = 0;
-                    collider_stuff = (
-                        Collider::cuboid(0.35, 1.3, 1.1),
-                        Transform::from_translation(Vec3::new(0.0, 2.30, 0.5)),
-                    );
-                }
-                speed = 1.125;
+        let species = spawn_event.animal_type.species();
+        let variant = &species.variants[rng.random_range(0..species.variants.len())];
+        let Some(scene) = scene_assets.animal(variant.name) else {
+            warn!("No scene loaded for a {}", variant.name);
+            continue;
+        };
+
+        let agent_size = variant.agent_size(species.scale);
+
+        let area = SearchArea::Anywhere {
+            margin: ANIMAL_CONFIG.spawn_margin,
+        };
+        // The collider is scaled along with the model.
+        let radius = variant.radius() * species.scale;
+        let constraints = PlacementConstraints::new()
+            .on_navmesh(agent_size)
+            .clear_of_colliders()
+            .min_distance_from(PlacementKind::Animal, 2. * radius)
+            .preferring(species.biome_preference);
+        let translation = match placement.place(radius, area, &constraints) {
+            Ok(translation) => translation,
+            Err(PlacementError::NavMeshNotReady(_)) => {
+                waiting_for_navmesh.push(spawn_event);
+                continue;
             }
-        }
-
-        let move_and_eat = Steps::build()
-            .label("MoveAndEat")
-            .step(MoveToNearest::<Fruit>::new())
-            .step(Eat::new(90.0, 0.5));
+            Err(err) => {
+                warn!(
+                    "Couldn't find anywhere to put a {:?}: {}",
+                    spawn_event.animal_type, err
+                );
+                continue;
+            }
+        };
+        let collider = Collider::cuboid(
+            variant.collider_size.x,
+            variant.collider_size.y,
+            variant.collider_size.z,
+        );
+        let collider_stuff = (
+            collider.clone(),
+            Transform::from_translation(variant.collider_offset),
+        );
 
-        commands
+        let animal = commands
             .spawn((
-                Name::new(name),
-                SceneRoot(asset),
+                Name::new(variant.name),
+                SceneRoot(scene),
                 Transform::from_translation(translation)
                     .with_rotation(rotation)
-                    .with_scale(Vec3::splat(0.25)),
+                    .with_scale(Vec3::splat(species.scale)),
                 Animal {
-                    animal_type: spawn_event.animal_type.clone(),
+                    animal_type: spawn_event.animal_type,
                 },
-                Age::new(&time_controller),
-                Speed::new(speed),
+                Age::from_days(&time_controller, spawn_event.age_days),
+                LifeCycle::new(&species.life_cycle),
+                Personality::random(),
+                species.speed,
+                species.stamina.clone(),
+                species.steering,
                 AnimationData {
-                    animation_key: name,
-                    animation_index,
+                    animation_key: variant.name,
+                    animation_index: 0,
+                    animation_speed: 1.0,
                 },
-                Thinker::build()
-                    .label("AnimaThinker")
-                    .picker(FirstToScore { threshold: 0.8 })
-                    .when(Hungry, move_and_eat),
+                species.thinker(),
+                AvoidanceAgent::new(variant.radius()),
+                BrowseHeight(variant.browse_height),
+                agent_size,
+                species.area_costs,
+                CharacterController::new(collider, variant.collider_offset),
             ))
             .with_children(|parent| {
                 parent.spawn(collider_stuff);
             })
-            .observe(unstuck_animals)
+            .observe(return_to_navmesh)
             .observe(pick_guy)
             .observe(asset_load_handle)
             .observe(animate_movement)
             .observe(idle_on_stop)
-            .observe(on_arrive);
+            .observe(on_arrive)
+            .observe(apply_life_stage)
+            .observe(become_carcass)
+            .id();
+        placement.claim(PlacementKind::Animal, animal, translation);
+
+        match species.diet {
+            Diet::Herbivore => commands.entity(animal).insert(Prey),
+            Diet::Carnivore => commands.entity(animal).insert(Predator),
+        };
     }
 }
 
 struct AnimalConfig {
-    initial_count: u32,
+    /// How far in from the edge of the world animals are placed when they start out.
+    spawn_margin: f32,
+    /// How far in from the edge of the world `goto_random` sends them.
+    wander_margin: f32,
 }
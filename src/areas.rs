@@ -0,0 +1,564 @@
+use std::{cmp::Ordering, collections::BinaryHeap, f32::consts::FRAC_PI_2};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    bounds::WorldBounds, despawn::Despawning, schedule::StartupSet, spatial::SpatialIndex,
+    terrain::Terrain, vegetation::Tree,
+};
+
+const AREA_CONFIG: AreaConfig = AreaConfig {
+    cell_size: 0.5,
+    forest_radius: 3.,
+    forest_min_trees: 3,
+    escape_cost: 20.,
+    ponds: &[(Vec2::new(-12., 8.), 3.5), (Vec2::new(10., -14.), 2.5)],
+};
+
+pub struct AreasPlugin;
+
+impl Plugin for AreasPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NavArea>()
+            .register_type::<AreaCosts>()
+            .init_resource::<AreaMap>()
+            .init_resource::<StaleAreas>()
+            .add_observer(mark_despawning_stale)
+            .add_systems(Startup, spawn_ponds.in_set(StartupSet::StartupRoundA))
+            .add_systems(FixedUpdate, update_area_map);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum AreaType {
+    /// Open ground.
+    #[default]
+    Meadow,
+    /// Ground with enough trees around.
+    Forest,
+    Water,
+}
+
+impl AreaType {
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            AreaType::Meadow => 1.0,
+            AreaType::Forest => 0.7,
+            AreaType::Water => 0.4,
+        }
+    }
+}
+
+/// A circular patch of a given area type, e.g. a pond.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct NavArea {
+    pub area: AreaType,
+    pub radius: f32,
+}
+
+/// How much a species dislikes walking through each area type, as a multiplier on
+/// distance. Infinite means it won't go there at all.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct AreaCosts {
+    pub meadow: f32,
+    pub forest: f32,
+    pub water: f32,
+}
+
+impl Default for AreaCosts {
+    fn default() -> Self {
+        Self {
+            meadow: 1.,
+            forest: 1.,
+            water: 1.,
+        }
+    }
+}
+
+impl AreaCosts {
+    pub fn cost(&self, area: AreaType) -> f32 {
+        match area {
+            AreaType::Meadow => self.meadow,
+            AreaType::Forest => self.forest,
+            AreaType::Water => self.water,
+        }
+    }
+
+    /// The same costs, except that `area` is passable at a steep price if it wasn't
+    /// before.
+    fn escaping(&self, area: AreaType) -> Self {
+        let mut costs = *self;
+        let cost = match area {
+            AreaType::Meadow => &mut costs.meadow,
+            AreaType::Forest => &mut costs.forest,
+            AreaType::Water => &mut costs.water,
+        };
+        if !cost.is_finite() {
+            *cost = AREA_CONFIG.escape_cost;
+        }
+        costs
+    }
+
+    fn cheapest(&self) -> f32 {
+        self.meadow.min(self.forest).min(self.water)
+    }
+}
+
+/// Area type of every cell of a grid over the XZ plane. Anything outside the grid
+/// is meadow.
+#[derive(Resource, Clone, Debug)]
+pub struct AreaMap {
+    half_extent: f32,
+    cell_size: f32,
+    cells_per_side: i32,
+    cells: Vec<AreaType>,
+}
+
+impl FromWorld for AreaMap {
+    fn from_world(world: &mut World) -> Self {
+        let bounds = world.resource::<WorldBounds>();
+        AreaMap::new(bounds.half_extent, AREA_CONFIG.cell_size)
+    }
+}
+
+impl AreaMap {
+    pub fn new(half_extent: f32, cell_size: f32) -> Self {
+        let cells_per_side = (2. * half_extent / cell_size).ceil() as i32;
+        Self {
+            half_extent,
+            cell_size,
+            cells_per_side,
+            cells: vec![AreaType::Meadow; (cells_per_side * cells_per_side) as usize],
+        }
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec2 {
+        IVec2::new(
+            ((position.x + self.half_extent) / self.cell_size).floor() as i32,
+            ((position.z + self.half_extent) / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn center_of(&self, cell: IVec2) -> Vec3 {
+        Vec3::new(
+            (cell.x as f32 + 0.5) * self.cell_size - self.half_extent,
+            0.,
+            (cell.y as f32 + 0.5) * self.cell_size - self.half_extent,
+        )
+    }
+
+    fn index_of(&self, cell: IVec2) -> Option<usize> {
+        let inside = (0..self.cells_per_side).contains(&cell.x)
+            && (0..self.cells_per_side).contains(&cell.y);
+        inside.then(|| (cell.y * self.cells_per_side + cell.x) as usize)
+    }
+
+    fn set(&mut self, cell: IVec2, area: AreaType) {
+        if let Some(index) = self.index_of(cell) {
+            self.cells[index] = area;
+        }
+    }
+
+    fn area_of(&self, cell: IVec2) -> AreaType {
+        self.index_of(cell)
+            .map_or(AreaType::Meadow, |index| self.cells[index])
+    }
+
+    pub fn area_at(&self, position: Vec3) -> AreaType {
+        self.area_of(self.cell_of(position))
+    }
+
+    /// Cost of walking from `from` through each of `waypoints` in turn.
+    pub fn path_cost(&self, costs: &AreaCosts, from: Vec3, waypoints: &[Vec3]) -> f32 {
+        let mut total = 0.;
+        let mut previous = from;
+        for waypoint in waypoints {
+            total += self.segment_cost(costs, previous, *waypoint);
+            previous = *waypoint;
+        }
+        total
+    }
+
+    fn segment_cost(&self, costs: &AreaCosts, from: Vec3, to: Vec3) -> f32 {
+        let length = from.xz().distance(to.xz());
+        let steps = (length / (0.5 * self.cell_size)).ceil().max(1.) as u32;
+        let step_length = length / steps as f32;
+        (0..steps)
+            .map(|step| {
+                let t = (step as f32 + 0.5) / steps as f32;
+                costs.cost(self.area_at(from.lerp(to, t))) * step_length
+            })
+            .sum()
+    }
+
+    /// Cheapest route from `from` to `to` over the grid, only through cells whose
+    /// centers are `walkable`, with the zig-zags pulled straight. Returns the
+    /// waypoints after `from` and the route's cost.
+    pub fn cheapest_path(
+        &self,
+        costs: &AreaCosts,
+        from: Vec3,
+        to: Vec3,
+        walkable: impl Fn(Vec3) -> bool,
+    ) -> Option<(Vec<Vec3>, f32)> {
+        let start = self.cell_of(from);
+        let goal = self.cell_of(to);
+        self.index_of(start)?;
+        self.index_of(goal)?;
+        // Something that's ended up where it would never go, like a deer pushed into a
+        // pond, still has to be able to get back out.
+        let costs = &costs.escaping(self.area_of(start));
+
+        let cell_cost = |cell: IVec2| {
+            // The ends are where the agent is and wants to be, so always let them in.
+            if cell != start && cell != goal && !walkable(self.center_of(cell)) {
+                return f32::INFINITY;
+            }
+            costs.cost(self.area_of(cell))
+        };
+        let heuristic =
+            |cell: IVec2| self.center_of(cell).distance(self.center_of(goal)) * costs.cheapest();
+
+        let mut best: HashMap<IVec2, (f32, IVec2)> = HashMap::default();
+        let mut open = BinaryHeap::new();
+        best.insert(start, (0., start));
+        open.push(OpenCell {
+            estimate: heuristic(start),
+            cost: 0.,
+            cell: start,
+        });
+
+        while let Some(OpenCell { cost, cell, .. }) = open.pop() {
+            if cell == goal {
+                break;
+            }
+            if best.get(&cell).is_some_and(|(known, _)| *known < cost) {
+                continue;
+            }
+            for offset in NEIGHBOR_OFFSETS {
+                let next = cell + offset;
+                if self.index_of(next).is_none() {
+                    continue;
+                }
+                let step_cost = 0.5 * (cell_cost(cell) + cell_cost(next));
+                if !step_cost.is_finite() {
+                    continue;
+                }
+                let next_cost = cost + step_cost * self.cell_size * offset.as_vec2().length();
+                if best.get(&next).is_none_or(|(known, _)| next_cost < *known) {
+                    best.insert(next, (next_cost, cell));
+                    open.push(OpenCell {
+                        estimate: next_cost + heuristic(next),
+                        cost: next_cost,
+                        cell: next,
+                    });
+                }
+            }
+        }
+
+        // Walk back from the goal, then swap the end cells for the exact points.
+        best.get(&goal)?;
+        let mut cells = vec![goal];
+        while let Some(&(_, previous)) = best.get(cells.last()?) {
+            if previous == *cells.last()? {
+                break;
+            }
+            cells.push(previous);
+        }
+        cells.reverse();
+        let mut points: Vec<Vec3> = cells.iter().map(|cell| self.center_of(*cell)).collect();
+        points[0] = from;
+        *points.last_mut()? = to;
+
+        let waypoints = self.pull_straight(costs, &points, &walkable);
+        let cost = self.path_cost(costs, from, &waypoints);
+        Some((waypoints, cost))
+    }
+
+    /// Skips grid points where going straight is walkable and no more expensive.
+    fn pull_straight(
+        &self,
+        costs: &AreaCosts,
+        points: &[Vec3],
+        walkable: &impl Fn(Vec3) -> bool,
+    ) -> Vec<Vec3> {
+        let mut waypoints = Vec::new();
+        let mut anchor = 0;
+        while anchor < points.len() - 1 {
+            let mut furthest = anchor + 1;
+            let mut zig_zag_cost = self.segment_cost(costs, points[anchor], points[anchor + 1]);
+            for candidate in anchor + 2..points.len() {
+                zig_zag_cost += self.segment_cost(costs, points[candidate - 1], points[candidate]);
+                let straight_cost = self.segment_cost(costs, points[anchor], points[candidate]);
+                let clear = self.segment_walkable(points[anchor], points[candidate], walkable);
+                if clear && straight_cost <= zig_zag_cost * 1.001 {
+                    furthest = candidate;
+                }
+            }
+            waypoints.push(points[furthest]);
+            anchor = furthest;
+        }
+        waypoints
+    }
+
+    fn segment_walkable(&self, from: Vec3, to: Vec3, walkable: &impl Fn(Vec3) -> bool) -> bool {
+        let steps = (from.distance(to) / (0.5 * self.cell_size)).ceil().max(1.) as u32;
+        (1..steps).all(|step| walkable(from.lerp(to, step as f32 / steps as f32)))
+    }
+}
+
+const NEIGHBOR_OFFSETS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// Min-heap entry for the A* open set.
+#[derive(Debug)]
+struct OpenCell {
+    estimate: f32,
+    cost: f32,
+    cell: IVec2,
+}
+
+impl PartialEq for OpenCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+fn spawn_ponds(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    terrain: Res<Terrain>,
+) {
+    let water = materials.add(Color::srgb(0.25, 0.45, 0.8));
+    for (center, radius) in AREA_CONFIG.ponds {
+        commands.spawn((
+            Name::new("Pond"),
+            Mesh3d(meshes.add(Circle::new(*radius))),
+            MeshMaterial3d(water.clone()),
+            Transform::from_xyz(
+                center.x,
+                terrain.height_at(Vec3::new(center.x, 0., center.y)) + 0.05,
+                center.y,
+            )
+            .with_rotation(Quat::from_rotation_x(-FRAC_PI_2)),
+            NavArea {
+                area: AreaType::Water,
+                radius: *radius,
+            },
+        ));
+    }
+}
+
+/// Parts of the area map waiting to be redrawn because something in them is going
+/// away.
+#[derive(Resource, Debug, Default)]
+struct StaleAreas {
+    all: bool,
+    around: Vec<Vec3>,
+}
+
+/// Trees and areas are gone by the time `update_area_map` runs, so note where they
+/// stood while they're still here.
+fn mark_despawning_stale(
+    trigger: Trigger<Despawning>,
+    mut stale: ResMut<StaleAreas>,
+    trees: Query<&Transform, With<Tree>>,
+    areas: Query<(), With<NavArea>>,
+) {
+    let entity = trigger.entity();
+    if let Ok(transform) = trees.get(entity) {
+        stale.around.push(transform.translation);
+    }
+    if areas.contains(entity) {
+        stale.all = true;
+    }
+}
+
+/// Redraws the area map around trees that come or go, or all of it when areas do.
+fn update_area_map(
+    mut area_map: ResMut<AreaMap>,
+    mut stale: ResMut<StaleAreas>,
+    tree_index: Res<SpatialIndex<Tree>>,
+    new_trees: Query<&Transform, Added<Tree>>,
+    areas: Query<(&Transform, Ref<NavArea>)>,
+) {
+    let StaleAreas { all, around } = std::mem::take(&mut *stale);
+    let mut changed_trees = around;
+    changed_trees.extend(new_trees.iter().map(|transform| transform.translation));
+
+    if all || areas.iter().any(|(_, area)| area.is_changed()) {
+        let mut map = AreaMap::new(area_map.half_extent, area_map.cell_size);
+        for y in 0..map.cells_per_side {
+            for x in 0..map.cells_per_side {
+                let cell = IVec2::new(x, y);
+                let area = area_for(&map, cell, &tree_index, &areas);
+                map.set(cell, area);
+            }
+        }
+        *area_map = map;
+        return;
+    }
+
+    // Only cells within `forest_radius` of a tree can have changed.
+    let radius = AREA_CONFIG.forest_radius;
+    for position in changed_trees {
+        let min = area_map.cell_of(position - Vec3::splat(radius));
+        let max = area_map.cell_of(position + Vec3::splat(radius));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+                if area_map.index_of(cell).is_none()
+                    || area_map.center_of(cell).xz().distance(position.xz()) > radius
+                {
+                    continue;
+                }
+                let area = area_for(&area_map, cell, &tree_index, &areas);
+                area_map.set(cell, area);
+            }
+        }
+    }
+}
+
+/// Whichever area covers the cell, or else forest or meadow depending on how many
+/// trees are around.
+fn area_for(
+    map: &AreaMap,
+    cell: IVec2,
+    tree_index: &SpatialIndex<Tree>,
+    areas: &Query<(&Transform, Ref<NavArea>)>,
+) -> AreaType {
+    let center = map.center_of(cell);
+    let covering = areas
+        .iter()
+        .find(|(transform, area)| center.xz().distance(transform.translation.xz()) <= area.radius)
+        .map(|(_, area)| area.area);
+    covering.unwrap_or_else(|| {
+        let trees = tree_index
+            .within_radius(center, AREA_CONFIG.forest_radius)
+            .len();
+        if trees >= AREA_CONFIG.forest_min_trees {
+            AreaType::Forest
+        } else {
+            AreaType::Meadow
+        }
+    })
+}
+
+struct AreaConfig {
+    cell_size: f32,
+    /// A spot counts as forest with at least `forest_min_trees` within `forest_radius`.
+    forest_radius: f32,
+    forest_min_trees: usize,
+    /// Cost per distance of getting out of an area the agent would never go into.
+    escape_cost: f32,
+    /// Center and radius of each pond.
+    ponds: &'static [(Vec2, f32)],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COSTS: AreaCosts = AreaCosts {
+        meadow: 1.,
+        forest: 5.,
+        water: f32::INFINITY,
+    };
+
+    /// A 10x10 map with a vertical band of `area` at x in 4..6, from z = 0 to `z_end`.
+    fn banded(area: AreaType, z_end: i32) -> AreaMap {
+        let mut map = AreaMap::new(5., 1.);
+        for z in 0..z_end {
+            map.set(IVec2::new(4, z), area);
+            map.set(IVec2::new(5, z), area);
+        }
+        map
+    }
+
+    #[test]
+    fn straight_across_open_ground() {
+        let map = AreaMap::new(5., 1.);
+        let from = Vec3::new(-4., 0., 0.);
+        let to = Vec3::new(4., 0., 0.);
+        let (waypoints, cost) = map.cheapest_path(&COSTS, from, to, |_| true).unwrap();
+        assert_eq!(waypoints, vec![to]);
+        assert!((cost - 8.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn goes_around_impassable_water() {
+        let map = banded(AreaType::Water, 8);
+        let from = Vec3::new(-4., 0., -4.);
+        let to = Vec3::new(4., 0., -4.);
+        let (waypoints, cost) = map.cheapest_path(&COSTS, from, to, |_| true).unwrap();
+        assert!(cost.is_finite());
+        assert!(waypoints.iter().any(|p| p.z > 3.), "{waypoints:?}");
+    }
+
+    #[test]
+    fn detours_around_costly_forest_when_cheaper() {
+        let map = banded(AreaType::Forest, 6);
+        let from = Vec3::new(-4., 0., -4.);
+        let to = Vec3::new(4., 0., -4.);
+        let (_, cost) = map.cheapest_path(&COSTS, from, to, |_| true).unwrap();
+        let straight = map.path_cost(&COSTS, from, &[to]);
+        assert!(cost < straight, "{cost} vs {straight}");
+    }
+
+    #[test]
+    fn fails_when_walled_off() {
+        let map = banded(AreaType::Water, 10);
+        let from = Vec3::new(-4., 0., 0.);
+        let to = Vec3::new(4., 0., 0.);
+        assert!(map.cheapest_path(&COSTS, from, to, |_| true).is_none());
+    }
+
+    #[test]
+    fn gets_out_of_water_it_was_pushed_into() {
+        let map = banded(AreaType::Water, 10);
+        let from = Vec3::new(0., 0., 0.);
+        let to = Vec3::new(-4., 0., 0.);
+        let (waypoints, cost) = map.cheapest_path(&COSTS, from, to, |_| true).unwrap();
+        assert_eq!(waypoints.last(), Some(&to));
+        assert!(cost.is_finite());
+    }
+
+    #[test]
+    fn respects_walkable() {
+        let map = AreaMap::new(5., 1.);
+        let from = Vec3::new(-4., 0., -4.);
+        let to = Vec3::new(4., 0., -4.);
+        let wall = |p: Vec3| !(p.x > -1. && p.x < 1. && p.z < 3.);
+        let (waypoints, _) = map.cheapest_path(&COSTS, from, to, wall).unwrap();
+        let mut previous = from;
+        for waypoint in waypoints {
+            assert!(map.segment_walkable(previous, waypoint, &wall));
+            previous = waypoint;
+        }
+    }
+}
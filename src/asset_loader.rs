@@ -10,6 +10,19 @@ pub struct SceneAssets {
     pub fruit: Handle<Scene>,
     pub deer: Handle<Scene>,
     pub stag: Handle<Scene>,
+    pub wolf: Handle<Scene>,
+}
+
+impl SceneAssets {
+    /// The scene for an animal variant, if one's loaded under that name.
+    pub fn animal(&self, key: &str) -> Option<Handle<Scene>> {
+        match key {
+            "deer" => Some(self.deer.clone()),
+            "stag" => Some(self.stag.clone()),
+            "wolf" => Some(self.wolf.clone()),
+            _ => None,
+        }
+    }
 }
 
 pub struct AssetLoaderPlugin;
@@ -36,6 +49,7 @@ pub struct Animations(HashMap<&'static str, AnimationObject>);
 pub struct AnimationData {
     pub animation_key: &'static str,
     pub animation_index: usize,
+    pub animation_speed: f32,
 }
 
 fn load_assets(
@@ -48,36 +62,16 @@ fn load_assets(
     let fruit_scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset("Fruit.glb"));
     let deer_scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset("animals/Deer.glb"));
     let stag_scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset("animals/Stag.glb"));
+    let wolf_scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset("animals/Wolf.glb"));
 
-    let (deer_graph, deer_node_indices) = AnimationGraph::from_clips([
-        asset_server.load(GltfAssetLabel::Animation(11).from_asset("animals/Deer.glb")), //idle
-        asset_server.load(GltfAssetLabel::Animation(9).from_asset("animals/Deer.glb")),  //walk
-        asset_server.load(GltfAssetLabel::Animation(4).from_asset("animals/Deer.glb")),  //run
-    ]);
-    let deer_graph_handle = graphs.add(deer_graph);
-
-    let (stag_graph, stag_node_indices) = AnimationGraph::from_clips([
-        asset_server.load(GltfAssetLabel::Animation(11).from_asset("animals/Stag.glb")), //idle
-        asset_server.load(GltfAssetLabel::Animation(9).from_asset("animals/Stag.glb")),  //walk
-        asset_server.load(GltfAssetLabel::Animation(4).from_asset("animals/Stag.glb")),  //run
-    ]);
-    let stag_graph_handle = graphs.add(stag_graph);
+    let deer_animations = animal_animations(&asset_server, &mut graphs, "animals/Deer.glb");
+    let stag_animations = animal_animations(&asset_server, &mut graphs, "animals/Stag.glb");
+    let wolf_animations = animal_animations(&asset_server, &mut graphs, "animals/Wolf.glb");
 
     let animations = HashMap::from([
-        (
-            "deer",
-            AnimationObject {
-                animations: deer_node_indices,
-                graph_handle: deer_graph_handle,
-            },
-        ),
-        (
-            "stag",
-            AnimationObject {
-                animations: stag_node_indices,
-                graph_handle: stag_graph_handle,
-            },
-        ),
+        ("deer", deer_animations),
+        ("stag", stag_animations),
+        ("wolf", wolf_animations),
     ]);
 
     // Keep our animation graph in a Resource so that it can be inserted onto
@@ -89,9 +83,29 @@ fn load_assets(
         fruit: fruit_scene,
         deer: deer_scene,
         stag: stag_scene,
+        wolf: wolf_scene,
     };
 }
 
+/// All the animal models share the same clip layout, so the idle, walk and run
+/// clips always end up at indices 0, 1 and 2 of `AnimationData::animation_index`.
+fn animal_animations(
+    asset_server: &AssetServer,
+    graphs: &mut Assets<AnimationGraph>,
+    path: &'static str,
+) -> AnimationObject {
+    let (graph, node_indices) = AnimationGraph::from_clips([
+        asset_server.load(GltfAssetLabel::Animation(11).from_asset(path)), //idle
+        asset_server.load(GltfAssetLabel::Animation(9).from_asset(path)),  //walk
+        asset_server.load(GltfAssetLabel::Animation(4).from_asset(path)),  //run
+    ]);
+
+    AnimationObject {
+        animations: node_indices,
+        graph_handle: graphs.add(graph),
+    }
+}
+
 pub fn asset_load_handle(
     trigger: Trigger<SceneInstanceReady>,
     mut commands: Commands,
@@ -115,7 +129,8 @@ pub fn asset_load_handle(
                         Duration::from_millis(250),
                         // Duration::ZERO,
                     )
-                    .repeat();
+                    .repeat()
+                    .set_speed(adata.animation_speed);
                 commands
                     .entity(child)
                     .insert(AnimationGraphHandle(ani_set.graph_handle.clone()))
@@ -140,7 +155,7 @@ fn apply_animation_changes(
 
                 player.stop_all();
 
-                player.play(node).repeat();
+                player.play(node).repeat().set_speed(anim.animation_speed);
                 break;
             }
         }
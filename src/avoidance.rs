@@ -0,0 +1,443 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    areas::AreaMap,
+    movement::{Destination, Gait, Speed, Steering, select_gait, update_position},
+    navigation::{AgentSize, EntityPath, NavMeshes},
+    spatial::{SpatialIndex, SpatialIndexPlugin},
+    time_control::TimeController,
+};
+
+const AVOIDANCE_CONFIG: AvoidanceConfig = AvoidanceConfig {
+    neighbor_distance: 3.0,
+    max_neighbors: 8,
+    time_horizon_secs: 2.0,
+    cell_size: 4.0,
+};
+
+const EPSILON: f32 = 0.00001;
+
+pub struct AvoidancePlugin;
+
+impl Plugin for AvoidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AvoidanceAgent>()
+            .add_plugins(SpatialIndexPlugin::<AvoidanceAgent>::new(
+                AVOIDANCE_CONFIG.cell_size,
+            ))
+            .add_systems(
+                FixedUpdate,
+                avoid_agents.after(select_gait).before(update_position),
+            );
+    }
+}
+
+/// Steers around other agents on the way to the `Destination`, treating each one
+/// as a circle on the ground.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct AvoidanceAgent {
+    /// Before the entity's scale is applied.
+    radius: f32,
+    /// Velocity on the XZ plane picked this tick, which the others take into
+    /// account next tick.
+    pub velocity: Vec2,
+}
+
+impl AvoidanceAgent {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+/// One of the agents taking part in ORCA, on the XZ plane.
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaAgent {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub radius: f32,
+}
+
+/// The half-plane of velocities allowed by one neighbor: everything to the left
+/// of `direction` going through `point`.
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    point: Vec2,
+    direction: Vec2,
+}
+
+fn avoid_agents(
+    time_controller: Res<TimeController>,
+    navmeshes: NavMeshes,
+    area_map: Res<AreaMap>,
+    index: Res<SpatialIndex<AvoidanceAgent>>,
+    mut query: Query<(
+        Entity,
+        &Transform,
+        &mut AvoidanceAgent,
+        Option<&Destination>,
+        Option<&EntityPath>,
+        Option<&Steering>,
+        &Speed,
+        &Gait,
+        Option<&AgentSize>,
+    )>,
+) {
+    let delta = time_controller.scaled_delta().as_secs_f32();
+    if delta <= 0. {
+        return;
+    }
+
+    // Everyone decides off last tick's velocities, so do all the reading first.
+    let agents: HashMap<Entity, OrcaAgent> = query
+        .iter()
+        .map(|(entity, transform, agent, ..)| {
+            (
+                entity,
+                OrcaAgent {
+                    position: transform.translation.xz(),
+                    velocity: agent.velocity,
+                    radius: agent.radius * transform.scale.x,
+                },
+            )
+        })
+        .collect();
+    let mut new_velocities = Vec::with_capacity(agents.len());
+
+    for (entity, transform, _, destination, path, steering, speed, gait, size) in query.iter() {
+        let Some(destination) = destination else {
+            new_velocities.push((entity, Vec2::ZERO));
+            continue;
+        };
+        let Some(&me) = agents.get(&entity) else {
+            continue;
+        };
+
+        let max_speed =
+            speed.for_gait(*gait) * area_map.area_at(transform.translation).speed_multiplier();
+        // Head the way the agent steers along its path, but never past the
+        // destination, or big time steps overshoot it.
+        let aim = steering.map_or(destination.value(), |steering| {
+            steering.aim(transform.translation, destination, path)
+        });
+        let to_destination = destination.value().xz().distance(me.position);
+        let preferred =
+            (aim.xz() - me.position).normalize_or_zero() * max_speed.min(to_destination / delta);
+
+        let neighbors: Vec<OrcaAgent> = index
+            .nearest_k(transform.translation, AVOIDANCE_CONFIG.max_neighbors + 1)
+            .into_iter()
+            .filter(|(other, position)| {
+                *other != entity
+                    && position.xz().distance(me.position) < AVOIDANCE_CONFIG.neighbor_distance
+            })
+            .filter_map(|(other, _)| agents.get(&other).copied())
+            .collect();
+
+        let mut velocity = orca_velocity(
+            &me,
+            &neighbors,
+            preferred,
+            max_speed,
+            AVOIDANCE_CONFIG.time_horizon_secs,
+            delta,
+        );
+        // Only follow the detour if it stays on walkable ground; otherwise fall back
+        // to the path, which already does.
+        if let Some(navmesh) = navmeshes.get(size.copied().unwrap_or_default()) {
+            let next = transform.translation + Vec3::new(velocity.x, 0., velocity.y) * delta;
+            if !navmesh.transformed_is_in_mesh(next) {
+                velocity = preferred;
+            }
+        }
+        new_velocities.push((entity, velocity));
+    }
+
+    for (entity, velocity) in new_velocities {
+        if let Ok((_, _, mut agent, ..)) = query.get_mut(entity) {
+            agent.velocity = velocity;
+        }
+    }
+}
+
+/// The velocity closest to `preferred` that won't collide with any of `neighbors`
+/// within `time_horizon` seconds, assuming they do their share of the avoiding.
+/// See van den Berg et al., "Reciprocal n-body Collision Avoidance".
+pub fn orca_velocity(
+    agent: &OrcaAgent,
+    neighbors: &[OrcaAgent],
+    preferred: Vec2,
+    max_speed: f32,
+    time_horizon: f32,
+    delta: f32,
+) -> Vec2 {
+    // Never look ahead less than one step, or large steps skip past each other.
+    let time_horizon = time_horizon.max(delta);
+    let inv_time_horizon = 1. / time_horizon;
+
+    let lines: Vec<Line> = neighbors
+        .iter()
+        .map(|other| {
+            let relative_position = other.position - agent.position;
+            let relative_velocity = agent.velocity - other.velocity;
+            let dist_sqrd = relative_position.length_squared();
+            let combined_radius = agent.radius + other.radius;
+            let combined_radius_sqrd = combined_radius * combined_radius;
+
+            let (direction, u) = if dist_sqrd > combined_radius_sqrd {
+                // Vector from the cutoff center to the relative velocity.
+                let w = relative_velocity - inv_time_horizon * relative_position;
+                let w_length_sqrd = w.length_squared();
+                let dot = w.dot(relative_position);
+
+                if dot < 0. && dot * dot > combined_radius_sqrd * w_length_sqrd {
+                    // Closest to the cutoff circle.
+                    let w_length = w_length_sqrd.sqrt();
+                    let unit_w = w / w_length;
+                    (
+                        Vec2::new(unit_w.y, -unit_w.x),
+                        (combined_radius * inv_time_horizon - w_length) * unit_w,
+                    )
+                } else {
+                    // Closest to one of the legs of the cone.
+                    let leg = (dist_sqrd - combined_radius_sqrd).sqrt();
+                    let direction = if det(relative_position, w) > 0. {
+                        Vec2::new(
+                            relative_position.x * leg - relative_position.y * combined_radius,
+                            relative_position.x * combined_radius + relative_position.y * leg,
+                        ) / dist_sqrd
+                    } else {
+                        -Vec2::new(
+                            relative_position.x * leg + relative_position.y * combined_radius,
+                            -relative_position.x * combined_radius + relative_position.y * leg,
+                        ) / dist_sqrd
+                    };
+                    (
+                        direction,
+                        relative_velocity.dot(direction) * direction - relative_velocity,
+                    )
+                }
+            } else {
+                // Already overlapping, so get apart within this step.
+                let inv_delta = 1. / delta;
+                let w = relative_velocity - inv_delta * relative_position;
+                let unit_w = w.try_normalize().unwrap_or(Vec2::X);
+                (
+                    Vec2::new(unit_w.y, -unit_w.x),
+                    (combined_radius * inv_delta - w.length()) * unit_w,
+                )
+            };
+
+            Line {
+                point: agent.velocity + 0.5 * u,
+                direction,
+            }
+        })
+        .collect();
+
+    let (velocity, failed_line) = linear_program2(&lines, max_speed, preferred, false);
+    if failed_line < lines.len() {
+        linear_program3(&lines, failed_line, max_speed, velocity)
+    } else {
+        velocity
+    }
+}
+
+fn det(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Best velocity on `lines[line_index]` that satisfies all the lines before it.
+fn linear_program1(
+    lines: &[Line],
+    line_index: usize,
+    radius: f32,
+    optimal: Vec2,
+    direction_optimal: bool,
+) -> Option<Vec2> {
+    let line = lines[line_index];
+    let dot = line.point.dot(line.direction);
+    let discriminant = dot * dot + radius * radius - line.point.length_squared();
+    if discriminant < 0. {
+        // The max speed circle doesn't reach the line.
+        return None;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let mut t_left = -dot - sqrt_discriminant;
+    let mut t_right = -dot + sqrt_discriminant;
+
+    for other in &lines[..line_index] {
+        let denominator = det(line.direction, other.direction);
+        let numerator = det(other.direction, line.point - other.point);
+
+        if denominator.abs() <= EPSILON {
+            // Parallel lines.
+            if numerator < 0. {
+                return None;
+            }
+            continue;
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0. {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+        if t_left > t_right {
+            return None;
+        }
+    }
+
+    let t = if direction_optimal {
+        if optimal.dot(line.direction) > 0. {
+            t_right
+        } else {
+            t_left
+        }
+    } else {
+        line.direction
+            .dot(optimal - line.point)
+            .clamp(t_left, t_right)
+    };
+    Some(line.point + t * line.direction)
+}
+
+/// Velocity closest to `optimal`, or furthest along it if `direction_optimal`,
+/// within `radius` and every line. Also returns the index of the first line it
+/// couldn't satisfy, or `lines.len()` if it satisfied them all.
+fn linear_program2(
+    lines: &[Line],
+    radius: f32,
+    optimal: Vec2,
+    direction_optimal: bool,
+) -> (Vec2, usize) {
+    let mut result = if direction_optimal {
+        optimal * radius
+    } else {
+        optimal.clamp_length_max(radius)
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if det(line.direction, line.point - result) > 0. {
+            // The result is on the wrong side of this line.
+            match linear_program1(lines, i, radius, optimal, direction_optimal) {
+                Some(velocity) => result = velocity,
+                None => return (result, i),
+            }
+        }
+    }
+    (result, lines.len())
+}
+
+/// When there's no velocity satisfying every line, picks the one that breaks them
+/// by the least.
+fn linear_program3(lines: &[Line], begin_line: usize, radius: f32, mut result: Vec2) -> Vec2 {
+    let mut distance = 0.;
+
+    for i in begin_line..lines.len() {
+        let line = lines[i];
+        if det(line.direction, line.point - result) <= distance {
+            continue;
+        }
+
+        let projected: Vec<Line> = lines[..i]
+            .iter()
+            .filter_map(|other| {
+                let determinant = det(line.direction, other.direction);
+                let point = if determinant.abs() <= EPSILON {
+                    if line.direction.dot(other.direction) > 0. {
+                        // Same direction, so this one adds nothing.
+                        return None;
+                    }
+                    0.5 * (line.point + other.point)
+                } else {
+                    line.point
+                        + (det(other.direction, line.point - other.point) / determinant)
+                            * line.direction
+                };
+                Some(Line {
+                    point,
+                    direction: (other.direction - line.direction).normalize_or_zero(),
+                })
+            })
+            .collect();
+
+        let (velocity, failed_line) = linear_program2(
+            &projected,
+            radius,
+            Vec2::new(-line.direction.y, line.direction.x),
+            true,
+        );
+        // Failing here can only be down to rounding, so keep what we had.
+        if failed_line == projected.len() {
+            result = velocity;
+        }
+        distance = det(line.direction, line.point - result);
+    }
+    result
+}
+
+struct AvoidanceConfig {
+    /// Other agents further than this are ignored.
+    neighbor_distance: f32,
+    max_neighbors: usize,
+    /// How far ahead collisions are avoided, in sim seconds.
+    time_horizon_secs: f32,
+    cell_size: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(x: f32, y: f32, velocity: Vec2) -> OrcaAgent {
+        OrcaAgent {
+            position: Vec2::new(x, y),
+            velocity,
+            radius: 0.5,
+        }
+    }
+
+    #[test]
+    fn no_neighbors_keeps_preferred() {
+        let me = agent(0., 0., Vec2::ZERO);
+        let velocity = orca_velocity(&me, &[], Vec2::new(1., 0.), 2., 2., 0.1);
+        assert!(velocity.distance(Vec2::new(1., 0.)) < 1e-5);
+    }
+
+    #[test]
+    fn respects_max_speed() {
+        let me = agent(0., 0., Vec2::ZERO);
+        let velocity = orca_velocity(&me, &[], Vec2::new(10., 0.), 2., 2., 0.1);
+        assert!(velocity.length() <= 2. + 1e-5);
+    }
+
+    #[test]
+    fn head_on_agents_sidestep() {
+        let me = agent(0., 0., Vec2::new(1., 0.));
+        let other = agent(3., 0., Vec2::new(-1., 0.));
+        let velocity = orca_velocity(&me, &[other], Vec2::new(1., 0.), 1.5, 2., 0.1);
+        assert!(velocity.y.abs() > 0.01, "{velocity:?}");
+    }
+
+    #[test]
+    fn overlapping_agents_separate() {
+        let me = agent(0., 0., Vec2::ZERO);
+        let other = agent(0.5, 0., Vec2::ZERO);
+        let velocity = orca_velocity(&me, &[other], Vec2::ZERO, 1., 2., 0.1);
+        assert!(velocity.x < 0., "{velocity:?}");
+    }
+
+    #[test]
+    fn unrelated_neighbor_changes_nothing() {
+        let me = agent(0., 0., Vec2::new(1., 0.));
+        let other = agent(0., 2.5, Vec2::new(1., 0.));
+        let velocity = orca_velocity(&me, &[other], Vec2::new(1., 0.), 1.5, 2., 0.1);
+        assert!(velocity.distance(Vec2::new(1., 0.)) < 1e-4, "{velocity:?}");
+    }
+}
@@ -0,0 +1,259 @@
+use bevy::prelude::*;
+
+use crate::{
+    bounds::{WorldBounds, WorldSeed},
+    terrain::{Terrain, fractal_noise},
+    vegetation::TreeSpecies,
+};
+
+const BIOME_CONFIG: BiomeMapConfig = BiomeMapConfig {
+    cell_size: 1.,
+    moisture_frequency: 0.03,
+    rockiness_frequency: 0.05,
+    octaves: 3,
+    wetland_max_height: -0.75,
+    wetland_min_moisture: 0.,
+    rocky_min_height: 1.5,
+    rocky_min_rockiness: 0.3,
+    woodland_min_moisture: 0.1,
+};
+
+static MEADOW: BiomeConfig = BiomeConfig {
+    tree_density: 0.15,
+    tree_species: &[(TreeSpecies::Oak, 1.)],
+    fruit_yield: 1.,
+    soil_nutrients: 0.5,
+    soil_moisture: 0.4,
+    ground_color: Color::srgb(0.529, 0.922, 0.643),
+};
+
+static WOODLAND: BiomeConfig = BiomeConfig {
+    tree_density: 1.,
+    tree_species: &[(TreeSpecies::Oak, 0.7), (TreeSpecies::Pine, 0.3)],
+    fruit_yield: 1.2,
+    soil_nutrients: 0.7,
+    soil_moisture: 0.6,
+    ground_color: Color::srgb(0.33, 0.62, 0.36),
+};
+
+static WETLAND: BiomeConfig = BiomeConfig {
+    tree_density: 0.4,
+    tree_species: &[(TreeSpecies::Willow, 1.)],
+    fruit_yield: 0.8,
+    soil_nutrients: 0.6,
+    soil_moisture: 0.9,
+    ground_color: Color::srgb(0.45, 0.58, 0.42),
+};
+
+static ROCKY: BiomeConfig = BiomeConfig {
+    tree_density: 0.1,
+    tree_species: &[(TreeSpecies::Pine, 1.)],
+    fruit_yield: 0.3,
+    soil_nutrients: 0.2,
+    soil_moisture: 0.2,
+    ground_color: Color::srgb(0.62, 0.62, 0.58),
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Biome {
+    #[default]
+    Meadow,
+    Woodland,
+    Wetland,
+    Rocky,
+}
+
+impl Biome {
+    pub fn config(&self) -> &'static BiomeConfig {
+        match self {
+            Biome::Meadow => &MEADOW,
+            Biome::Woodland => &WOODLAND,
+            Biome::Wetland => &WETLAND,
+            Biome::Rocky => &ROCKY,
+        }
+    }
+}
+
+/// How keen something is on each biome, from 0 (never) to 1.
+#[derive(Clone, Copy, Debug)]
+pub struct BiomeWeights {
+    pub meadow: f32,
+    pub woodland: f32,
+    pub wetland: f32,
+    pub rocky: f32,
+}
+
+impl BiomeWeights {
+    pub fn weight(&self, biome: Biome) -> f32 {
+        match biome {
+            Biome::Meadow => self.meadow,
+            Biome::Woodland => self.woodland,
+            Biome::Wetland => self.wetland,
+            Biome::Rocky => self.rocky,
+        }
+    }
+
+    /// How likely a tree is to take root in each biome.
+    pub fn tree_density() -> Self {
+        Self {
+            meadow: MEADOW.tree_density,
+            woodland: WOODLAND.tree_density,
+            wetland: WETLAND.tree_density,
+            rocky: ROCKY.tree_density,
+        }
+    }
+}
+
+/// Which biome every spot of the ground is in. Wet hollows are wetland, high or
+/// broken ground is rocky, and the rest is woodland or meadow depending on how
+/// much water it gets.
+#[derive(Resource, Clone, Debug)]
+pub struct BiomeMap {
+    half_extent: f32,
+    cell_size: f32,
+    cells_per_side: i32,
+    cells: Vec<Biome>,
+}
+
+impl FromWorld for BiomeMap {
+    fn from_world(world: &mut World) -> Self {
+        let half_extent = 0.5 * world.resource::<WorldBounds>().ground_size();
+        let seed = world.resource::<WorldSeed>().0;
+        let terrain = world.resource::<Terrain>();
+        BiomeMap::generate(seed, terrain, half_extent, BIOME_CONFIG.cell_size)
+    }
+}
+
+impl BiomeMap {
+    pub fn generate(seed: u64, terrain: &Terrain, half_extent: f32, cell_size: f32) -> Self {
+        let cells_per_side = (2. * half_extent / cell_size).ceil() as i32;
+        let mut map = Self {
+            half_extent,
+            cell_size,
+            cells_per_side,
+            cells: Vec::with_capacity((cells_per_side * cells_per_side) as usize),
+        };
+        // Decorrelate the layers from each other and from the terrain.
+        let moisture_seed = seed ^ 0x6D6F_6973_7475_7265;
+        let rockiness_seed = seed ^ 0x726F_636B_696E_6573;
+        for z in 0..cells_per_side {
+            for x in 0..cells_per_side {
+                let center = map.center_of(IVec2::new(x, z));
+                let point = center.xz();
+                let moisture = fractal_noise(
+                    moisture_seed,
+                    point,
+                    BIOME_CONFIG.moisture_frequency,
+                    BIOME_CONFIG.octaves,
+                );
+                let rockiness = fractal_noise(
+                    rockiness_seed,
+                    point,
+                    BIOME_CONFIG.rockiness_frequency,
+                    BIOME_CONFIG.octaves,
+                );
+                let biome = classify(terrain.height_at(center), moisture, rockiness);
+                map.cells.push(biome);
+            }
+        }
+        map
+    }
+
+    fn center_of(&self, cell: IVec2) -> Vec3 {
+        Vec3::new(
+            (cell.x as f32 + 0.5) * self.cell_size - self.half_extent,
+            0.,
+            (cell.y as f32 + 0.5) * self.cell_size - self.half_extent,
+        )
+    }
+
+    /// The biome at any point. Past the edge of the map it carries on the edge.
+    pub fn biome_at(&self, position: Vec3) -> Biome {
+        let last = self.cells_per_side - 1;
+        let x = (((position.x + self.half_extent) / self.cell_size).floor() as i32).clamp(0, last);
+        let z = (((position.z + self.half_extent) / self.cell_size).floor() as i32).clamp(0, last);
+        self.cells[(z * self.cells_per_side + x) as usize]
+    }
+}
+
+fn classify(height: f32, moisture: f32, rockiness: f32) -> Biome {
+    if height <= BIOME_CONFIG.wetland_max_height && moisture >= BIOME_CONFIG.wetland_min_moisture {
+        Biome::Wetland
+    } else if height >= BIOME_CONFIG.rocky_min_height
+        || rockiness >= BIOME_CONFIG.rocky_min_rockiness
+    {
+        Biome::Rocky
+    } else if moisture >= BIOME_CONFIG.woodland_min_moisture {
+        Biome::Woodland
+    } else {
+        Biome::Meadow
+    }
+}
+
+pub struct BiomeConfig {
+    /// How likely a tree is to take root here, from 0 to 1.
+    pub tree_density: f32,
+    /// Which trees grow here, with relative weights.
+    pub tree_species: &'static [(TreeSpecies, f32)],
+    /// Multiplier on how often trees here fruit.
+    pub fruit_yield: f64,
+    /// What the soil starts out with, from 0 to 1.
+    pub soil_nutrients: f32,
+    pub soil_moisture: f32,
+    pub ground_color: Color,
+}
+
+impl BiomeConfig {
+    /// Picks a tree species for this biome. `roll` is between 0 and 1.
+    pub fn pick_tree_species(&self, roll: f32) -> TreeSpecies {
+        let total: f32 = self.tree_species.iter().map(|(_, weight)| weight).sum();
+        let mut roll = roll * total;
+        for (species, weight) in self.tree_species {
+            if roll < *weight {
+                return *species;
+            }
+            roll -= weight;
+        }
+        self.tree_species
+            .last()
+            .map(|(species, _)| *species)
+            .unwrap_or_default()
+    }
+}
+
+struct BiomeMapConfig {
+    cell_size: f32,
+    moisture_frequency: f32,
+    rockiness_frequency: f32,
+    octaves: u32,
+    /// Ground this low and this wet is wetland.
+    wetland_max_height: f32,
+    wetland_min_moisture: f32,
+    /// Ground this high, or this broken up, is rocky.
+    rocky_min_height: f32,
+    rocky_min_rockiness: f32,
+    /// Anything else this wet is woodland, and drier than that meadow.
+    woodland_min_moisture: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_wet_ground_is_wetland_and_high_ground_rocky() {
+        assert_eq!(classify(-2., 0.2, 0.), Biome::Wetland);
+        assert_eq!(classify(-2., -0.2, 0.), Biome::Meadow);
+        assert_eq!(classify(2., 0.5, 0.), Biome::Rocky);
+        assert_eq!(classify(0., 0., 0.5), Biome::Rocky);
+        assert_eq!(classify(0., 0.2, 0.), Biome::Woodland);
+    }
+
+    #[test]
+    fn tree_species_picked_by_weight() {
+        assert_eq!(WOODLAND.pick_tree_species(0.), TreeSpecies::Oak);
+        assert_eq!(WOODLAND.pick_tree_species(0.69), TreeSpecies::Oak);
+        assert_eq!(WOODLAND.pick_tree_species(0.71), TreeSpecies::Pine);
+        assert_eq!(WOODLAND.pick_tree_species(1.), TreeSpecies::Pine);
+    }
+}
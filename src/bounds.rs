@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::controller::move_characters;
+
+pub struct WorldBoundsPlugin;
+
+impl Plugin for WorldBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WorldBounds>()
+            .register_type::<WorldSeed>()
+            .init_resource::<WorldBounds>()
+            .init_resource::<WorldSeed>()
+            .add_systems(FixedUpdate, keep_in_bounds.after(move_characters));
+    }
+}
+
+/// The square, centred on the origin, that everything lives in. The navmesh covers
+/// exactly this, so anything outside it can't path anywhere.
+#[derive(Resource, Clone, Copy, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct WorldBounds {
+    pub half_extent: f32,
+    /// How far the ground carries on past the edge, so the world doesn't end in a cliff.
+    pub ground_margin: f32,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            half_extent: 25.,
+            ground_margin: 25.,
+        }
+    }
+}
+
+impl WorldBounds {
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.x.abs() <= self.half_extent && point.z.abs() <= self.half_extent
+    }
+
+    /// Pulls a point back inside, keeping its height.
+    pub fn clamp(&self, point: Vec3) -> Vec3 {
+        Vec3::new(
+            point.x.clamp(-self.half_extent, self.half_extent),
+            point.y,
+            point.z.clamp(-self.half_extent, self.half_extent),
+        )
+    }
+
+    /// A random point on the ground at least `margin` in from the edge.
+    pub fn random_point(&self, rng: &mut impl Rng, margin: f32) -> Vec3 {
+        let half_extent = (self.half_extent - margin).max(0.);
+        if half_extent == 0. {
+            return Vec3::ZERO;
+        }
+        Vec3::new(
+            rng.random_range(-half_extent..half_extent),
+            0.,
+            rng.random_range(-half_extent..half_extent),
+        )
+    }
+
+    /// Corners of the playable area, counter-clockwise, as the navmesh wants them.
+    pub fn corners(&self) -> Vec<Vec2> {
+        let e = self.half_extent;
+        vec![
+            Vec2::new(-e, -e),
+            Vec2::new(e, -e),
+            Vec2::new(e, e),
+            Vec2::new(-e, e),
+        ]
+    }
+
+    pub fn ground_size(&self) -> f32 {
+        2. * (self.half_extent + self.ground_margin)
+    }
+}
+
+/// Everything generated about the world, like the shape of the land, comes from
+/// this, so the same seed always gives the same world.
+#[derive(Resource, Clone, Copy, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct WorldSeed(pub u64);
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self(42)
+    }
+}
+
+/// Marks things that must never leave the world, like animals and fruit.
+#[derive(Component, Default, Debug)]
+pub struct Bounded;
+
+fn keep_in_bounds(
+    bounds: Res<WorldBounds>,
+    mut query: Query<&mut Transform, (With<Bounded>, Changed<Transform>)>,
+) {
+    for mut transform in query.iter_mut() {
+        if !bounds.contains(transform.translation) {
+            transform.translation = bounds.clamp(transform.translation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_keeps_height_and_pulls_back_inside() {
+        let bounds = WorldBounds::default();
+        let clamped = bounds.clamp(Vec3::new(40., 1., -30.));
+
+        assert_eq!(clamped, Vec3::new(25., 1., -25.));
+        assert!(bounds.contains(clamped));
+    }
+
+    #[test]
+    fn random_points_respect_the_margin() {
+        let bounds = WorldBounds::default();
+        let mut rng = rand::rng();
+
+        for _ in 0..100 {
+            let point = bounds.random_point(&mut rng, 5.);
+            assert!(point.x.abs() <= 20. && point.z.abs() <= 20.);
+        }
+        assert_eq!(bounds.random_point(&mut rng, 30.), Vec3::ZERO);
+    }
+}
@@ -1,6 +1,8 @@
-use bevy::prelude::*;
+use bevy::{math::bounding::Aabb2d, prelude::*};
 use bevy_rts_camera::{RtsCamera, RtsCameraControls};
 
+use crate::bounds::WorldBounds;
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
@@ -9,9 +11,10 @@ impl Plugin for CameraPlugin {
     }
 }
 
-fn spawn_rts_camera(mut commands: Commands) {
+fn spawn_rts_camera(mut commands: Commands, bounds: Res<WorldBounds>) {
     commands.spawn((
         RtsCamera {
+            bounds: Aabb2d::new(Vec2::ZERO, Vec2::splat(bounds.half_extent)),
             height_max: 80.,
             height_min: 5.,
             min_angle: 0.65,
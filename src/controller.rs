@@ -0,0 +1,153 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::movement::update_position;
+
+const CONTROLLER_CONFIG: ControllerConfig = ControllerConfig {
+    max_slides: 4,
+    skin_width: 0.02,
+    ground_snap_height: 1.,
+    ground_snap_distance: 2.,
+};
+
+pub struct CharacterControllerPlugin;
+
+impl Plugin for CharacterControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CharacterContactEvent>()
+            .add_systems(FixedUpdate, move_characters.after(update_position));
+    }
+}
+
+#[derive(PhysicsLayer, Clone, Copy, Debug, Default)]
+pub enum CollisionLayer {
+    /// Everything that gets in the way: trees, animals.
+    #[default]
+    Default,
+    /// Only ever stood on, never bumped into.
+    Ground,
+}
+
+/// Moves a kinematic body by sweeping its collider along the way, so it slides
+/// along whatever it bumps into instead of going through it, and keeps it on the
+/// ground. Set `movement` and the controller does the rest on the same tick.
+#[derive(Component, Debug)]
+#[require(RigidBody(|| RigidBody::Kinematic))]
+pub struct CharacterController {
+    /// The body's collider, unscaled and relative to the entity.
+    shape: Collider,
+    offset: Vec3,
+    /// How far the entity wants to move this tick.
+    pub movement: Vec3,
+}
+
+impl CharacterController {
+    pub fn new(shape: Collider, offset: Vec3) -> Self {
+        Self {
+            shape,
+            offset,
+            movement: Vec3::ZERO,
+        }
+    }
+}
+
+/// Sent whenever a character bumps into something while moving.
+#[derive(Event, Debug)]
+pub struct CharacterContactEvent {
+    pub entity: Entity,
+    pub other: Entity,
+    /// Points away from `other`, towards the character.
+    pub normal: Vec3,
+}
+
+pub fn move_characters(
+    spatial_query: SpatialQuery,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut CharacterController,
+        Option<&Children>,
+    )>,
+    mut contact_event_writer: EventWriter<CharacterContactEvent>,
+) {
+    for (entity, mut transform, mut controller, children) in query.iter_mut() {
+        let movement = std::mem::take(&mut controller.movement).with_y(0.);
+        if movement == Vec3::ZERO {
+            continue;
+        }
+
+        // Don't bump into our own collider.
+        let filter = SpatialQueryFilter::from_mask(CollisionLayer::Default).with_excluded_entities(
+            children
+                .iter()
+                .flat_map(|c| c.iter())
+                .copied()
+                .chain([entity]),
+        );
+        let mut shape = controller.shape.clone();
+        shape.set_scale(transform.scale, 8);
+        let offset = transform.rotation * (controller.offset * transform.scale);
+
+        let mut position = transform.translation;
+        let mut remaining = movement;
+        for _ in 0..CONTROLLER_CONFIG.max_slides {
+            let Ok(direction) = Dir3::new(remaining) else {
+                break;
+            };
+            let distance = remaining.length();
+            let config = ShapeCastConfig {
+                max_distance: distance,
+                // Something that spawned on top of us shouldn't pin us in place.
+                ignore_origin_penetration: true,
+                ..default()
+            };
+            let Some(hit) = spatial_query.cast_shape(
+                &shape,
+                position + offset,
+                transform.rotation,
+                direction,
+                &config,
+                &filter,
+            ) else {
+                position += remaining;
+                break;
+            };
+
+            let travelled = (hit.distance - CONTROLLER_CONFIG.skin_width).max(0.);
+            position += direction * travelled;
+            let normal = hit.normal1.with_y(0.).normalize_or_zero();
+            contact_event_writer.send(CharacterContactEvent {
+                entity,
+                other: hit.entity,
+                normal,
+            });
+
+            // Slide along whatever's in the way with what's left.
+            remaining = direction * (distance - travelled);
+            remaining -= normal * remaining.dot(normal).min(0.);
+        }
+
+        let snap_height = CONTROLLER_CONFIG.ground_snap_height;
+        if let Some(ground) = spatial_query.cast_ray(
+            position + Vec3::Y * snap_height,
+            Dir3::NEG_Y,
+            snap_height + CONTROLLER_CONFIG.ground_snap_distance,
+            true,
+            &SpatialQueryFilter::from_mask(CollisionLayer::Ground),
+        ) {
+            position.y += snap_height - ground.distance;
+        }
+
+        transform.translation = position;
+    }
+}
+
+struct ControllerConfig {
+    max_slides: u32,
+    /// Gap kept between a character and whatever it bumps into.
+    skin_width: f32,
+    /// How far above its feet a character looks for the ground.
+    ground_snap_height: f32,
+    /// How far below its feet it still gets pulled down to the ground.
+    ground_snap_distance: f32,
+}
@@ -0,0 +1,73 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::life_cycle::DeathCause;
+
+pub struct DespawnPlugin;
+
+impl Plugin for DespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DespawnRequest>()
+            // Everything that might still be holding on to an entity has had its go by
+            // the end of the frame, so that's the only place anything gets despawned.
+            .add_systems(Last, process_despawn_requests);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DespawnCause {
+    Died(DeathCause),
+    Decayed,
+    Eaten,
+    /// Fell off something, and was replaced by a copy on the ground.
+    Fell,
+}
+
+/// Asks for an entity to be despawned at the end of the frame. Send this instead of
+/// despawning directly so that whatever refers to the entity gets a chance to let go.
+/// Until then it's still around, so strip whatever marker would have it counted or
+/// used again in the meantime.
+#[derive(Event, Debug)]
+pub struct DespawnRequest {
+    pub entity: Entity,
+    pub cause: DespawnCause,
+}
+
+impl DespawnRequest {
+    pub fn new(entity: Entity, cause: DespawnCause) -> Self {
+        Self { entity, cause }
+    }
+}
+
+/// Triggered on an entity right before it's despawned. Observe this to clear out
+/// references to it.
+#[derive(Event, Debug)]
+pub struct Despawning {
+    pub cause: DespawnCause,
+}
+
+fn process_despawn_requests(
+    mut commands: Commands,
+    mut despawn_request_reader: EventReader<DespawnRequest>,
+) {
+    let mut handled = HashSet::new();
+
+    for request in despawn_request_reader.read() {
+        // The same entity can be asked for more than once, e.g. eaten and decayed
+        // in the same tick.
+        if !handled.insert(request.entity) {
+            continue;
+        }
+        if commands.get_entity(request.entity).is_none() {
+            continue;
+        }
+
+        debug!("Despawning {:?} ({:?})", request.entity, request.cause);
+        commands.trigger_targets(
+            Despawning {
+                cause: request.cause,
+            },
+            request.entity,
+        );
+        commands.entity(request.entity).despawn_recursive();
+    }
+}
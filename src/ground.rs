@@ -1,6 +1,14 @@
+use avian3d::prelude::*;
 use bevy::prelude::*;
 
-use crate::schedule::StartupSet;
+use crate::{
+    biome::BiomeMap,
+    controller::CollisionLayer,
+    movement::{Destination, select_gait},
+    navigation::Obstacle,
+    schedule::StartupSet,
+    terrain::Terrain,
+};
 
 #[derive(Component)]
 pub struct Ground;
@@ -9,7 +17,11 @@ pub struct GroundPlugin;
 
 impl Plugin for GroundPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_ground.in_set(StartupSet::StartupRoundA));
+        app.init_resource::<Terrain>()
+            // Made from the terrain, so it has to come second.
+            .init_resource::<BiomeMap>()
+            .add_systems(Startup, spawn_ground.in_set(StartupSet::StartupRoundA))
+            .add_systems(FixedUpdate, snap_destinations_to_ground.before(select_gait));
     }
 }
 
@@ -17,11 +29,48 @@ fn spawn_ground(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    terrain: Res<Terrain>,
+    biome_map: Res<BiomeMap>,
 ) {
-    // plane
+    let mesh = terrain.mesh(|position| biome_map.biome_at(position).config().ground_color);
     commands.spawn((
-        Mesh3d(meshes.add(Plane3d::default().mesh().size(100., 100.))),
-        MeshMaterial3d(materials.add(Color::srgb(0.529, 0.922, 0.643))),
+        Name::new("Ground"),
+        Mesh3d(meshes.add(mesh)),
+        // The colour comes from the biomes.
+        MeshMaterial3d(materials.add(Color::WHITE)),
         Ground,
+        RigidBody::Static,
+        terrain.collider(),
+        CollisionLayers::new(CollisionLayer::Ground, LayerMask::ALL),
     ));
+
+    // Slopes too steep to walk get cut out of the navmesh. They aren't in any layer,
+    // so nothing bumps into them; the ground itself is what's in the way. The navmesh
+    // is cut where obstacles cross y=0, so these stand on it and reach past any hill.
+    let (steep_cells, cell_size) = terrain.steep_cells();
+    let height = 2. * (terrain.max_height() + 1.);
+    for center in steep_cells {
+        commands.spawn((
+            Name::new("Steep slope"),
+            Transform::from_translation(center.with_y(0.)),
+            Collider::cuboid(cell_size, height, cell_size),
+            CollisionLayers::NONE,
+            Obstacle,
+        ));
+    }
+}
+
+/// Paths come off the navmesh flat, so lift wherever animals are headed onto the
+/// ground.
+fn snap_destinations_to_ground(
+    terrain: Res<Terrain>,
+    mut query: Query<&mut Destination, Changed<Destination>>,
+) {
+    for mut destination in query.iter_mut() {
+        let value = destination.value();
+        let height = terrain.height_at(value);
+        if (value.y - height).abs() > f32::EPSILON {
+            *destination = Destination::new(value.with_y(height));
+        }
+    }
 }
@@ -1,7 +1,14 @@
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, EguiPlugin, egui};
 
-use crate::{age::Age, needs::Satiety, time_control::TimeController};
+use crate::{
+    age::Age,
+    despawn::Despawning,
+    life_cycle::LifeStage,
+    needs::Satiety,
+    season::{Calendar, Wind},
+    time_control::TimeController,
+};
 
 pub struct HUDPlugin;
 
@@ -10,6 +17,7 @@ impl Plugin for HUDPlugin {
         app.add_plugins(EguiPlugin)
             .insert_resource(PickedGuy { entity: None })
             .add_plugins(MeshPickingPlugin)
+            .add_observer(forget_despawned_guy)
             .add_systems(Update, ui_example_system);
     }
 }
@@ -17,21 +25,32 @@ impl Plugin for HUDPlugin {
 fn ui_example_system(
     mut contexts: EguiContexts,
     time_controller: Res<TimeController>,
+    calendar: Res<Calendar>,
+    wind: Res<Wind>,
     picked_guy: Res<PickedGuy>,
-    query: Query<(&Age, &Satiety, &Name)>,
+    query: Query<(&Age, &Satiety, &Name, Option<&LifeStage>)>,
 ) {
     egui::Window::new("World Time").show(contexts.ctx_mut(), |ui| {
         ui.label(time_controller.simulated_elapsed_time_string());
+        ui.label(format!(
+            "{:?} (day {:.0}), {:.1}°C, wind {:.0}%, trees {:?}",
+            calendar.season(),
+            calendar.day_of_year(),
+            calendar.temperature(),
+            100. * wind.strength(),
+            calendar.tree_phase(),
+        ));
     });
 
     if let Some(guy) = picked_guy.entity {
-        if let Ok((age, satiety, name)) = query.get(guy) {
+        if let Ok((age, satiety, name, stage)) = query.get(guy) {
             egui::Window::new("Guy").show(contexts.ctx_mut(), |ui| {
                 let guy_str = format!(
-                    "{}: {}\nAge: {}\nSatiety: {{ value: {:05.2}, per_min: {}}}",
+                    "{}: {}\nAge: {} ({:?})\nSatiety: {{ value: {:05.2}, per_min: {}}}",
                     name,
                     guy,
                     age.formatted_age_string(&time_controller),
+                    stage,
                     satiety.value,
                     satiety.drain_speed_per_sec,
                 );
@@ -57,3 +76,9 @@ pub struct PickedGuy {
 pub fn pick_guy(trigger: Trigger<Pointer<Click>>, mut picked_guy: ResMut<PickedGuy>) {
     picked_guy.entity = Some(trigger.entity());
 }
+
+fn forget_despawned_guy(trigger: Trigger<Despawning>, mut picked_guy: ResMut<PickedGuy>) {
+    if picked_guy.entity == Some(trigger.entity()) {
+        picked_guy.entity = None;
+    }
+}
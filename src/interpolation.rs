@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::schedule::PostUpdateSet;
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<InterpolationSettings>()
+            .init_resource::<InterpolationSettings>()
+            .add_observer(start_interpolating)
+            .add_systems(First, restore_simulation_transforms)
+            .add_systems(FixedLast, record_simulation_transforms)
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms.in_set(PostUpdateSet::Interpolated),
+            )
+            .add_systems(
+                Update,
+                toggle_interpolation
+                    .run_if(|keys: Res<ButtonInput<KeyCode>>| keys.just_pressed(KeyCode::KeyI)),
+            );
+    }
+}
+
+/// Turn this off to see where things really are in the simulation.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct InterpolationSettings {
+    pub enabled: bool,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Smooths out an entity that's moved in `FixedUpdate`, by drawing it somewhere
+/// between where it was at the last two ticks. The rest of the app never sees
+/// that: its `Transform` is put back at the start of every frame, and anything
+/// that moves it outside `FixedUpdate` moves it in the simulation too.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Interpolated {
+    previous: (Vec3, Quat),
+    current: (Vec3, Quat),
+}
+
+fn start_interpolating(
+    trigger: Trigger<OnAdd, Interpolated>,
+    mut query: Query<(&Transform, &mut Interpolated)>,
+) {
+    if let Ok((transform, mut interpolated)) = query.get_mut(trigger.entity()) {
+        let pose = (transform.translation, transform.rotation);
+        interpolated.previous = pose;
+        interpolated.current = pose;
+    }
+}
+
+fn restore_simulation_transforms(mut query: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in query.iter_mut() {
+        let (translation, rotation) = interpolated.current;
+        if transform.translation != translation || transform.rotation != rotation {
+            transform.translation = translation;
+            transform.rotation = rotation;
+        }
+    }
+}
+
+fn record_simulation_transforms(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = (transform.translation, transform.rotation);
+    }
+}
+
+fn interpolate_transforms(
+    settings: Res<InterpolationSettings>,
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let t = if settings.enabled {
+        fixed_time.overstep_fraction()
+    } else {
+        1.
+    };
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        let pose = (transform.translation, transform.rotation);
+        if pose != interpolated.current {
+            // Moved since the last tick, e.g. laid down when it died, so jump there.
+            interpolated.previous = pose;
+            interpolated.current = pose;
+            continue;
+        }
+        if interpolated.previous == interpolated.current {
+            // Keep still things from showing up as changed every frame.
+            continue;
+        }
+        let (previous_translation, previous_rotation) = interpolated.previous;
+        let (translation, rotation) = interpolated.current;
+        transform.translation = previous_translation.lerp(translation, t);
+        transform.rotation = previous_rotation.slerp(rotation, t);
+    }
+}
+
+fn toggle_interpolation(mut settings: ResMut<InterpolationSettings>) {
+    settings.enabled = !settings.enabled;
+    info!(
+        "Transform interpolation {}",
+        if settings.enabled { "on" } else { "off" }
+    );
+}
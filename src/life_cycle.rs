@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{age::Age, time_control::TimeController};
+
+const MORTALITY_CHECK_SIM_SECONDS: f32 = 60. * 60. * 24.;
+
+pub struct LifeCyclePlugin;
+
+impl Plugin for LifeCyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LifeStage>()
+            .insert_resource(MortalityTimer {
+                timer: Timer::from_seconds(MORTALITY_CHECK_SIM_SECONDS, TimerMode::Repeating),
+            })
+            .add_systems(FixedUpdate, (update_life_stages, age_related_mortality));
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct MortalityTimer {
+    timer: Timer,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum LifeStage {
+    Newborn,
+    Juvenile,
+    Adult,
+    Senescent,
+}
+
+/// One value for each life stage, like how fast an animal is or how much a tree
+/// fruits at that stage.
+#[derive(Debug)]
+pub struct PerStage<T> {
+    pub newborn: T,
+    pub juvenile: T,
+    pub adult: T,
+    pub senescent: T,
+}
+
+impl<T: Copy> PerStage<T> {
+    pub fn get(&self, stage: LifeStage) -> T {
+        match stage {
+            LifeStage::Newborn => self.newborn,
+            LifeStage::Juvenile => self.juvenile,
+            LifeStage::Adult => self.adult,
+            LifeStage::Senescent => self.senescent,
+        }
+    }
+}
+
+/// Multipliers applied to an animal's base stats while it's in a given stage.
+#[derive(Clone, Copy, Debug)]
+pub struct StageModifiers {
+    pub scale: f32,
+    pub speed: f32,
+    pub need_rate: f32,
+}
+
+/// Gompertz mortality curve: from `onset_days` on, the daily hazard starts at
+/// `initial_daily_hazard` and doubles every `doubling_days`.
+#[derive(Debug)]
+pub struct MortalityCurve {
+    pub onset_days: f32,
+    pub initial_daily_hazard: f32,
+    pub doubling_days: f32,
+}
+
+impl MortalityCurve {
+    pub fn daily_death_probability(&self, age_days: f32) -> f64 {
+        if age_days < self.onset_days {
+            return 0.;
+        }
+        let hazard = self.initial_daily_hazard as f64
+            * 2f64.powf(((age_days - self.onset_days) / self.doubling_days) as f64);
+        1. - (-hazard).exp()
+    }
+}
+
+#[derive(Debug)]
+pub struct LifeCycleConfig {
+    /// Age in days at which each stage after `Newborn` begins.
+    pub juvenile_days: f32,
+    pub adult_days: f32,
+    pub senescent_days: f32,
+    pub mortality: MortalityCurve,
+}
+
+impl LifeCycleConfig {
+    pub fn stage_at(&self, age_days: f32) -> LifeStage {
+        if age_days >= self.senescent_days {
+            LifeStage::Senescent
+        } else if age_days >= self.adult_days {
+            LifeStage::Adult
+        } else if age_days >= self.juvenile_days {
+            LifeStage::Juvenile
+        } else {
+            LifeStage::Newborn
+        }
+    }
+}
+
+/// Gives an entity with an `Age` life stages and lets it die of old age.
+/// The current stage is kept in a `LifeStage` component next to it.
+#[derive(Component, Debug)]
+pub struct LifeCycle {
+    config: &'static LifeCycleConfig,
+}
+
+impl LifeCycle {
+    pub fn new(config: &'static LifeCycleConfig) -> Self {
+        Self { config }
+    }
+}
+
+/// Triggered on an entity when its life stage changes. `from` is `None` the first
+/// time the stage is worked out, right after spawning.
+#[derive(Event, Debug)]
+pub struct LifeStageChangedEvent {
+    pub from: Option<LifeStage>,
+    pub to: LifeStage,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    OldAge,
+    Predation,
+    /// Crowded out by bigger trees.
+    Shade,
+}
+
+/// Triggered on an entity when it dies. Observers decide what's left behind.
+#[derive(Event, Debug)]
+pub struct DeathEvent {
+    pub cause: DeathCause,
+}
+
+fn update_life_stages(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    mut query: Query<(Entity, &LifeCycle, &Age, Option<&mut LifeStage>)>,
+) {
+    for (entity, life_cycle, age, stage) in query.iter_mut() {
+        let new_stage = life_cycle
+            .config
+            .stage_at(age.age_days(&time_controller) as f32);
+        let from = match stage {
+            Some(stage) if *stage == new_stage => continue,
+            Some(mut stage) => {
+                let from = *stage;
+                *stage = new_stage;
+                Some(from)
+            }
+            None => {
+                commands.entity(entity).insert(new_stage);
+                None
+            }
+        };
+
+        debug!("{:?} is now {:?}", entity, new_stage);
+        commands.trigger_targets(
+            LifeStageChangedEvent {
+                from,
+                to: new_stage,
+            },
+            entity,
+        );
+    }
+}
+
+fn age_related_mortality(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    mut mortality_timer: ResMut<MortalityTimer>,
+    query: Query<(Entity, &LifeCycle, &Age)>,
+) {
+    mortality_timer.timer.tick(time_controller.scaled_delta());
+    if !mortality_timer.timer.just_finished() {
+        return;
+    }
+
+    let mut rng = rand::rng();
+    for (entity, life_cycle, age) in query.iter() {
+        let probability = life_cycle
+            .config
+            .mortality
+            .daily_death_probability(age.age_days(&time_controller) as f32);
+        if probability > 0. && rng.random_bool(probability) {
+            commands.trigger_targets(
+                DeathEvent {
+                    cause: DeathCause::OldAge,
+                },
+                entity,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: LifeCycleConfig = LifeCycleConfig {
+        juvenile_days: 10.,
+        adult_days: 100.,
+        senescent_days: 1000.,
+        mortality: MortalityCurve {
+            onset_days: 1000.,
+            initial_daily_hazard: 0.01,
+            doubling_days: 100.,
+        },
+    };
+
+    #[test]
+    fn stage_boundaries() {
+        assert_eq!(CONFIG.stage_at(0.), LifeStage::Newborn);
+        assert_eq!(CONFIG.stage_at(10.), LifeStage::Juvenile);
+        assert_eq!(CONFIG.stage_at(99.9), LifeStage::Juvenile);
+        assert_eq!(CONFIG.stage_at(100.), LifeStage::Adult);
+        assert_eq!(CONFIG.stage_at(5000.), LifeStage::Senescent);
+    }
+
+    #[test]
+    fn no_mortality_before_onset() {
+        assert_eq!(CONFIG.mortality.daily_death_probability(999.), 0.);
+    }
+
+    #[test]
+    fn mortality_rises_with_age() {
+        let at_onset = CONFIG.mortality.daily_death_probability(1000.);
+        let doubled = CONFIG.mortality.daily_death_probability(1100.);
+        let ancient = CONFIG.mortality.daily_death_probability(3000.);
+
+        assert!((at_onset - (1. - (-0.01f64).exp())).abs() < 1e-9);
+        assert!(doubled > at_onset);
+        assert!(ancient > 0.99);
+        assert!(ancient <= 1.);
+    }
+}
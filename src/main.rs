@@ -1,33 +1,61 @@
 mod age;
 mod animal;
+mod areas;
 mod asset_loader;
+mod avoidance;
+mod biome;
+mod bounds;
 mod camera;
+mod controller;
 mod debug;
+mod despawn;
 mod ground;
 mod hud;
+mod interpolation;
+mod life_cycle;
 mod light;
 mod movement;
 mod navigation;
 mod needs;
+mod personality;
+mod placement;
+mod predation;
 mod schedule;
+mod season;
+mod soil;
+mod spatial;
+mod species;
+mod terrain;
 mod time_control;
+mod utility;
 mod vegetation;
 
 use age::Age;
 use animal::AnimalPlugin;
+use areas::AreasPlugin;
 use asset_loader::AssetLoaderPlugin;
 use avian3d::prelude::*;
+use avoidance::AvoidancePlugin;
 use bevy::{log::LogPlugin, prelude::*};
 use bevy_rts_camera::RtsCameraPlugin;
+use bounds::WorldBoundsPlugin;
 use camera::CameraPlugin;
+use controller::CharacterControllerPlugin;
 use debug::DebugPlugin;
+use despawn::DespawnPlugin;
 use ground::GroundPlugin;
 use hud::HUDPlugin;
+use interpolation::InterpolationPlugin;
+use life_cycle::LifeCyclePlugin;
 use light::LightPlugin;
 use movement::MovementPlugin;
 use navigation::NavigationPlugin;
 use needs::NeedsPlugin;
+use personality::PersonalityPlugin;
+use predation::PredationPlugin;
 use schedule::SchedulePlugin;
+use season::SeasonPlugin;
+use soil::SoilPlugin;
 use time_control::TimeControlPlugin;
 use vegetation::VegetationPlugin;
 
@@ -43,12 +71,23 @@ fn main() {
             ..default()
         }))
         .add_plugins(PhysicsPlugins::default())
+        .add_plugins(DespawnPlugin)
+        .add_plugins(WorldBoundsPlugin)
         .add_plugins(NavigationPlugin)
+        .add_plugins(AreasPlugin)
         .add_plugins(NeedsPlugin)
+        .add_plugins(PredationPlugin)
+        .add_plugins(LifeCyclePlugin)
+        .add_plugins(PersonalityPlugin)
         .add_plugins(LightPlugin)
         .add_plugins(SchedulePlugin)
+        .add_plugins(SeasonPlugin)
         .add_plugins(GroundPlugin)
+        .add_plugins(SoilPlugin)
         .add_plugins(MovementPlugin)
+        .add_plugins(CharacterControllerPlugin)
+        .add_plugins(InterpolationPlugin)
+        .add_plugins(AvoidancePlugin)
         .add_plugins(RtsCameraPlugin)
         .add_plugins(CameraPlugin)
         .add_plugins(AssetLoaderPlugin)
@@ -1,6 +1,22 @@
 use bevy::prelude::*;
 
-use crate::{asset_loader::AnimationData, time_control::TimeController};
+use crate::{
+    areas::AreaMap,
+    asset_loader::AnimationData,
+    avoidance::AvoidanceAgent,
+    controller::CharacterController,
+    navigation::EntityPath,
+    needs::{CRITICAL_SATIETY, Satiety},
+    terrain::Terrain,
+    time_control::TimeController,
+};
+
+/// Goals further away than this are trotted to rather than walked to.
+const TROT_DISTANCE: f32 = 15.0;
+/// Fraction of max stamina an exhausted animal has to recover before it can run again.
+const RECOVERED_STAMINA_FRACTION: f32 = 0.5;
+/// The way the models face when they aren't rotated.
+const MODEL_FORWARD: Vec3 = Vec3::Z;
 
 pub struct MovementPlugin;
 
@@ -8,7 +24,14 @@ impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Destination>()
             .register_type::<Speed>()
-            .add_systems(FixedUpdate, update_position);
+            .register_type::<Gait>()
+            .register_type::<Stamina>()
+            .register_type::<Steering>()
+            .add_systems(
+                FixedUpdate,
+                (select_gait, update_position, update_stamina).chain(),
+            )
+            .add_systems(FixedUpdate, animate_gait);
     }
 }
 
@@ -21,19 +44,164 @@ impl Destination {
     pub fn new(value: Vec3) -> Self {
         Self { value }
     }
+
+    pub fn value(&self) -> Vec3 {
+        self.value
+    }
 }
 
-#[derive(Component, Debug, Reflect)]
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[require(Gait)]
 pub struct Speed {
-    value: f32,
+    walk: f32,
+    trot: f32,
+    run: f32,
 }
 
 impl Speed {
-    pub fn new(value: f32) -> Self {
-        Self { value }
+    pub const fn new(walk: f32, trot: f32, run: f32) -> Self {
+        Self { walk, trot, run }
+    }
+
+    pub fn scaled(&self, factor: f32) -> Self {
+        Self::new(self.walk * factor, self.trot * factor, self.run * factor)
+    }
+
+    pub fn for_gait(&self, gait: Gait) -> f32 {
+        match gait {
+            Gait::Walk => self.walk,
+            Gait::Trot => self.trot,
+            Gait::Run => self.run,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Gait {
+    #[default]
+    Walk,
+    Trot,
+    Run,
+}
+
+impl Gait {
+    pub fn animation_index(&self) -> usize {
+        match self {
+            Gait::Walk | Gait::Trot => 1,
+            Gait::Run => 2,
+        }
+    }
+
+    /// There's no trot clip, so trotting plays the walk clip faster.
+    pub fn animation_speed(&self) -> f32 {
+        match self {
+            Gait::Trot => 1.6,
+            Gait::Walk | Gait::Run => 1.0,
+        }
+    }
+}
+
+/// Running drains stamina. Once it runs out the animal is exhausted and can't run
+/// again until it has recovered `RECOVERED_STAMINA_FRACTION` of it.
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct Stamina {
+    pub value: f32,
+    max: f32,
+    run_cost_per_sec: f32,
+    recovery_per_sec: f32,
+    exhausted: bool,
+}
+
+impl Stamina {
+    pub const fn new(max: f32, run_cost_per_sec: f32, recovery_per_sec: f32) -> Self {
+        Self {
+            value: max,
+            max,
+            run_cost_per_sec,
+            recovery_per_sec,
+            exhausted: false,
+        }
+    }
+}
+
+/// How an animal gets up to speed, turns and pulls up. It only turns so tightly
+/// for how fast it's going, and slows down for sharp turns and to stop at the
+/// end of its path.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+pub struct Steering {
+    turn_radius: f32,
+    acceleration: f32,
+    deceleration: f32,
+    /// How far along the path it aims, which rounds off the corners.
+    look_ahead: f32,
+    /// How close to the end of its path counts as there.
+    arrival_distance: f32,
+    speed: f32,
+}
+
+impl Steering {
+    pub const fn new(
+        turn_radius: f32,
+        acceleration: f32,
+        deceleration: f32,
+        look_ahead: f32,
+        arrival_distance: f32,
+    ) -> Self {
+        Self {
+            turn_radius,
+            acceleration,
+            deceleration,
+            look_ahead,
+            arrival_distance,
+            speed: 0.,
+        }
+    }
+
+    /// Fastest it can go and still pull up at a goal `distance` away. It aims to stop
+    /// halfway into the arrival distance, so it doesn't crawl the last bit.
+    fn braking_speed(&self, distance: f32) -> f32 {
+        (2. * self.deceleration * (distance - 0.5 * self.arrival_distance).max(0.)).sqrt()
+    }
+
+    /// The point on the ground it steers for: `look_ahead` along the way to
+    /// `destination` and then the rest of `path`.
+    pub fn aim(
+        &self,
+        position: Vec3,
+        destination: &Destination,
+        path: Option<&EntityPath>,
+    ) -> Vec3 {
+        let upcoming = path.map(|path| path.upcoming()).into_iter().flatten();
+        look_ahead_point(
+            position.with_y(0.),
+            std::iter::once(destination.value)
+                .chain(upcoming)
+                .map(|p| p.with_y(0.)),
+            self.look_ahead,
+        )
     }
 }
 
+/// The point `distance` along the polyline from `from` through `waypoints`, or its
+/// last point if it's shorter than that.
+fn look_ahead_point(from: Vec3, waypoints: impl IntoIterator<Item = Vec3>, distance: f32) -> Vec3 {
+    let mut left = distance;
+    let mut point = from;
+    for next in waypoints {
+        let leg = point.distance(next);
+        if leg >= left {
+            return point.move_towards(next, left);
+        }
+        left -= leg;
+        point = next;
+    }
+    point
+}
+
+/// The entity is in a hurry, e.g. fleeing or chasing, and will run if it has the stamina.
+#[derive(Component, Debug, Default)]
+pub struct Urgent;
+
 #[derive(Event, Debug)]
 pub struct ArrivedEvent;
 
@@ -43,49 +211,208 @@ pub fn on_arrive(trigger: Trigger<ArrivedEvent>, mut query: Query<Entity>, mut c
     }
 }
 
-fn update_position(
+pub fn select_gait(
+    mut query: Query<(
+        &mut Gait,
+        &Transform,
+        &Destination,
+        &Stamina,
+        Option<&EntityPath>,
+        Option<&Satiety>,
+        Has<Urgent>,
+    )>,
+) {
+    for (mut gait, transform, destination, stamina, path, satiety, urgent) in query.iter_mut() {
+        let goal = path.and_then(|p| p.goal()).unwrap_or(destination.value);
+        let far = transform.translation.distance_squared(goal) > TROT_DISTANCE * TROT_DISTANCE;
+        let starving = satiety.is_some_and(|s| s.value < CRITICAL_SATIETY);
+
+        let new_gait = if urgent && !stamina.exhausted {
+            Gait::Run
+        } else if urgent || starving || far {
+            Gait::Trot
+        } else {
+            Gait::Walk
+        };
+        gait.set_if_neq(new_gait);
+    }
+}
+
+pub fn update_position(
     time_control: Res<TimeController>,
-    mut query: Query<(&mut Transform, &Destination, &Speed, Entity)>,
+    area_map: Res<AreaMap>,
+    terrain: Res<Terrain>,
+    mut query: Query<(
+        &mut Transform,
+        &mut Steering,
+        &Destination,
+        &Speed,
+        &Gait,
+        Option<&EntityPath>,
+        Option<&AvoidanceAgent>,
+        Option<&mut CharacterController>,
+        Entity,
+    )>,
     mut commands: Commands,
 ) {
-    for (mut transform, destination, speed, entity) in query.iter_mut() {
-        if transform.translation.distance_squared(destination.value) > 1. {
-            let delta = time_control.scaled_delta().as_secs_f32();
-            let one_eighty: f32 = 180.0;
-            let one_eighty = one_eighty.to_radians();
-            let mut looking_at = transform.looking_at(destination.value, Dir3::Y);
-            looking_at.rotate_y(one_eighty);
-            let angle: f32 = 0.05 * delta;
-            let angle = angle.to_degrees();
-            let rotation = transform
-                .rotation
-                .rotate_towards(looking_at.rotation, angle);
-            let diff = (rotation - transform.rotation).length();
-            if diff > 0.1 {
-                transform.rotation = rotation;
-            } else {
-                transform.rotation = rotation;
-                let d = speed.value * delta;
-                let new_translation = transform.translation.move_towards(destination.value, d);
-                transform.translation = new_translation;
-            }
+    let delta = time_control.scaled_delta().as_secs_f32();
+    if delta <= 0. {
+        return;
+    }
+
+    for (
+        mut transform,
+        mut steering,
+        destination,
+        speed,
+        gait,
+        path,
+        avoidance,
+        controller,
+        entity,
+    ) in query.iter_mut()
+    {
+        let position = transform.translation.with_y(0.);
+        let target = destination.value.with_y(0.);
+        let distance = position.distance(target);
+        let last_leg = path.is_none_or(|path| path.goal().is_none());
+
+        // Waypoints along the way only need passing close by; the look-ahead has
+        // already started turning for the next one.
+        let arrival_distance = if last_leg {
+            steering.arrival_distance
         } else {
+            steering.arrival_distance.max(0.5 * steering.look_ahead)
+        };
+        if distance <= arrival_distance {
+            if last_leg {
+                steering.speed = 0.;
+            }
             commands.trigger_targets(ArrivedEvent, entity);
+            continue;
+        }
+
+        let aim = steering.aim(position, destination, path);
+        let mut max_speed =
+            speed.for_gait(*gait) * area_map.area_at(transform.translation).speed_multiplier();
+        let mut desired = (aim - position).xz().normalize_or_zero();
+        if let Some(agent) = avoidance.filter(|agent| agent.velocity != Vec2::ZERO) {
+            // Avoidance starts out from the same aim, so this only changes it to
+            // dodge someone.
+            desired = agent.velocity.normalize();
+            max_speed = max_speed.min(agent.velocity.length());
+        }
+
+        // Turn towards where it wants to go, only as tightly as its speed allows.
+        // Even standing still it can turn at a walk.
+        let heading = (transform.rotation * MODEL_FORWARD)
+            .xz()
+            .normalize_or(desired);
+        let turn_speed = steering.speed.max(speed.for_gait(Gait::Walk));
+        let max_turn = turn_speed / steering.turn_radius * delta;
+        let turn = heading.angle_to(desired).clamp(-max_turn, max_turn);
+        let heading = Vec2::from_angle(turn).rotate(heading);
+        transform.rotation = Quat::from_rotation_y(heading.x.atan2(heading.y));
+
+        // Ease off for sharp turns and when pulling up at the end.
+        let mut target_speed = max_speed * heading.dot(desired).max(0.);
+        if last_leg {
+            target_speed = target_speed.min(steering.braking_speed(distance));
+        }
+        steering.speed = if target_speed > steering.speed {
+            (steering.speed + steering.acceleration * delta).min(target_speed)
+        } else {
+            (steering.speed - steering.deceleration * delta).max(target_speed)
+        };
+
+        // Big time steps would otherwise overshoot the waypoint.
+        let step = (steering.speed * delta).min(distance);
+        let movement = Vec3::new(heading.x, 0., heading.y) * step;
+        match controller {
+            // Let it work out what's in the way.
+            Some(mut controller) => controller.movement = movement,
+            None => {
+                let translation = transform.translation + movement;
+                transform.translation = translation.with_y(terrain.height_at(translation));
+            }
+        }
+    }
+}
+
+fn update_stamina(
+    time_control: Res<TimeController>,
+    mut query: Query<(&mut Stamina, &Gait, Has<Destination>)>,
+) {
+    let delta = time_control.scaled_delta().as_secs_f32();
+    for (mut stamina, gait, moving) in query.iter_mut() {
+        if moving && *gait == Gait::Run {
+            stamina.value = (stamina.value - stamina.run_cost_per_sec * delta).max(0.);
+            if stamina.value == 0. {
+                stamina.exhausted = true;
+            }
+        } else if stamina.value < stamina.max {
+            stamina.value = (stamina.value + stamina.recovery_per_sec * delta).min(stamina.max);
+            if stamina.value >= stamina.max * RECOVERED_STAMINA_FRACTION {
+                stamina.exhausted = false;
+            }
         }
     }
 }
 
 pub fn animate_movement(
     trigger: Trigger<OnAdd, Destination>,
-    mut query: Query<&mut AnimationData>,
+    mut query: Query<(&mut AnimationData, &Gait)>,
 ) {
-    if let Ok(mut anim) = query.get_mut(trigger.entity()) {
-        anim.animation_index = 1;
+    if let Ok((mut anim, gait)) = query.get_mut(trigger.entity()) {
+        anim.animation_index = gait.animation_index();
+        anim.animation_speed = gait.animation_speed();
+    }
+}
+
+fn animate_gait(mut query: Query<(&mut AnimationData, &Gait), (Changed<Gait>, With<Destination>)>) {
+    for (mut anim, gait) in query.iter_mut() {
+        anim.animation_index = gait.animation_index();
+        anim.animation_speed = gait.animation_speed();
     }
 }
 
 pub fn idle_on_stop(trigger: Trigger<OnRemove, Destination>, mut query: Query<&mut AnimationData>) {
     if let Ok(mut anim) = query.get_mut(trigger.entity()) {
         anim.animation_index = 0;
+        anim.animation_speed = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_ahead_follows_the_path_round_corners() {
+        let waypoints = [Vec3::new(2., 0., 0.), Vec3::new(2., 0., 4.)];
+
+        assert_eq!(
+            look_ahead_point(Vec3::ZERO, waypoints, 1.),
+            Vec3::new(1., 0., 0.)
+        );
+        assert_eq!(
+            look_ahead_point(Vec3::ZERO, waypoints, 3.),
+            Vec3::new(2., 0., 1.)
+        );
+        assert_eq!(
+            look_ahead_point(Vec3::ZERO, waypoints, 10.),
+            Vec3::new(2., 0., 4.)
+        );
+    }
+
+    #[test]
+    fn braking_speed_still_moves_at_the_arrival_distance() {
+        let steering = Steering::new(1., 2., 2., 1., 0.5);
+
+        assert_eq!(steering.braking_speed(0.), 0.);
+        assert_eq!(steering.braking_speed(0.25), 0.);
+        // v² = 2ad
+        assert_eq!(steering.braking_speed(0.5), 1.);
+        assert_eq!(steering.braking_speed(1.25), 2.);
     }
 }
@@ -1,9 +1,40 @@
 use avian3d::{math::FRAC_PI_2, prelude::*};
-use bevy::{math::vec2, prelude::*};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+    utils::HashMap,
+};
 use rand::Rng;
 use vleue_navigator::prelude::*;
 
-use crate::movement::Destination;
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::{
+    areas::{AreaCosts, AreaMap},
+    bounds::WorldBounds,
+    despawn::Despawning,
+    movement::Destination,
+    time_control::TimeController,
+};
+
+const PATH_REQUEST_CONFIG: PathRequestConfig = PathRequestConfig {
+    max_per_tick: 32,
+    max_attempts: 3,
+    retry_wait_secs: 2.0,
+    same_request_tolerance: 0.5,
+};
+
+const STUCK_CONFIG: StuckConfig = StuckConfig {
+    check_sim_seconds: 10.,
+    min_ticks: 20,
+    min_progress: 0.25,
+    nudge_radii: &[0.5, 1.0, 1.5, 2.0, 3.0],
+    nudge_directions: 8,
+};
+
+/// Spacing of the points checked along a path when the navmesh changes.
+const PATH_VALIDATION_STEP: f32 = 0.25;
 
 #[derive(Component)]
 pub struct Obstacle;
@@ -14,36 +45,96 @@ impl Plugin for NavigationPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(VleueNavigatorPlugin)
             .register_type::<EntityPath>()
+            .register_type::<PathRequestStatus>()
+            .register_type::<AgentSize>()
+            .init_resource::<PathRequestQueue>()
             .add_event::<FindPathEvent>()
+            .add_event::<CancelPathEvent>()
             .add_event::<NoPathFoundEvent>()
+            .add_observer(forget_despawned_path_request)
             .add_plugins(NavmeshUpdaterPlugin::<Collider, Obstacle>::default())
             .insert_resource(ClearColor(Color::srgb(0.05, 0.05, 0.1)))
             .add_systems(Startup, setup)
-            .add_systems(FixedUpdate, find_path)
+            .add_systems(
+                FixedUpdate,
+                (
+                    queue_path_requests,
+                    dispatch_path_requests,
+                    apply_path_results,
+                    revalidate_paths,
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, detect_stuck)
             .add_systems(FixedUpdate, traverse_path);
     }
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, bounds: Res<WorldBounds>) {
     let obstacle_size = 0.5;
 
-    commands.spawn((
-        NavMeshSettings {
-            // Define the outer borders of the navmesh.
-            fixed: Triangulation::from_outer_edges(&[
-                vec2(-25.0, -25.0),
-                vec2(25.0, -25.0),
-                vec2(25.0, 25.0),
-                vec2(-25.0, 25.0),
-            ]),
-            agent_radius: 0.25,
-            simplify: 0.005,
-            merge_steps: 0,
-            ..default()
-        },
-        NavMeshUpdateMode::Debounced(1.0),
-        Transform::from_xyz(0.0, 0.0, 0.0).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
-    ));
+    // One navmesh per size class, all carved out by the same `Obstacle`s.
+    for size in AgentSize::ALL {
+        commands.spawn((
+            NavMeshSettings {
+                // Define the outer borders of the navmesh.
+                fixed: Triangulation::from_outer_edges(&bounds.corners()),
+                agent_radius: size.agent_radius(),
+                simplify: 0.005,
+                merge_steps: 0,
+                ..default()
+            },
+            NavMeshUpdateMode::Debounced(1.0),
+            Transform::from_xyz(0.0, 0.0, 0.0).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+            ManagedNavMesh::from_id(size as u128),
+            size,
+        ));
+    }
+}
+
+/// Which navmesh an agent paths on. Each class's navmesh keeps the agent's center
+/// `agent_radius` away from obstacles, so bigger animals don't get routed through
+/// gaps they can't fit through. Agents without one are `Small`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum AgentSize {
+    #[default]
+    Small,
+    Large,
+}
+
+impl AgentSize {
+    pub const ALL: [AgentSize; 2] = [AgentSize::Small, AgentSize::Large];
+
+    /// The smallest class an agent of the given radius fits in.
+    pub fn for_radius(radius: f32) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|size| radius <= size.agent_radius())
+            .unwrap_or(AgentSize::Large)
+    }
+
+    fn agent_radius(&self) -> f32 {
+        match self {
+            AgentSize::Small => 0.25,
+            AgentSize::Large => 0.5,
+        }
+    }
+}
+
+/// Looks up the navmesh for a size class.
+#[derive(SystemParam)]
+pub struct NavMeshes<'w, 's> {
+    assets: Res<'w, Assets<NavMesh>>,
+    meshes: Query<'w, 's, (&'static ManagedNavMesh, &'static AgentSize)>,
+}
+
+impl NavMeshes<'_, '_> {
+    pub fn get(&self, size: AgentSize) -> Option<&NavMesh> {
+        self.meshes
+            .iter()
+            .find(|(_, mesh_size)| **mesh_size == size)
+            .and_then(|(navmesh, _)| self.assets.get(navmesh))
+    }
 }
 
 #[derive(Event, Debug)]
@@ -63,9 +154,144 @@ impl FindPathEvent {
     }
 }
 
+/// Drops the entity's pending or in-flight path request, if it has one. Paths
+/// already handed over as an `EntityPath` are left alone.
+#[derive(Event, Debug)]
+pub struct CancelPathEvent {
+    entity: Entity,
+}
+
+impl CancelPathEvent {
+    pub fn new(entity: Entity) -> Self {
+        Self { entity }
+    }
+}
+
+/// Triggered on the requester once its `EntityPath` has been inserted.
+#[derive(Event, Debug)]
+pub struct PathFoundEvent;
+
+/// Triggered on the requester once its path request has run out of attempts.
 #[derive(Event, Debug)]
 pub struct NoPathFoundEvent;
 
+/// Where the requester's latest `FindPathEvent` is at.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum PathRequestStatus {
+    Pending,
+    Found,
+    Failed,
+}
+
+#[derive(Debug)]
+struct PathRequest {
+    entity: Entity,
+    from_point: Vec3,
+    to_point: Vec3,
+    size: AgentSize,
+    costs: AreaCosts,
+    attempts: u32,
+    /// Navmesh generation and time of the last attempt, if it failed.
+    last_failure: Option<(u32, f32)>,
+}
+
+impl PathRequest {
+    /// Whether this asks for much the same path as `other`.
+    fn matches(&self, other: &PathRequest) -> bool {
+        let tolerance = PATH_REQUEST_CONFIG.same_request_tolerance;
+        self.size == other.size
+            && self.from_point.distance(other.from_point) <= tolerance
+            && self.to_point.distance(other.to_point) <= tolerance
+    }
+}
+
+/// A path being worked out on the `AsyncComputeTaskPool`.
+#[derive(Debug)]
+struct PathTask {
+    request: PathRequest,
+    /// Navmesh generation the path is being worked out on.
+    generation: u32,
+    task: Task<Option<Vec<Vec3>>>,
+}
+
+/// Copy of one size class's navmesh, shared with the path tasks.
+#[derive(Debug)]
+struct NavMeshSnapshot {
+    /// Bumped every time the navmesh finishes building.
+    generation: u32,
+    navmesh: Arc<NavMesh>,
+}
+
+/// `FindPathEvent`s waiting on the navmesh. There's at most one per requester; a
+/// newer request replaces the older one but keeps its place in line.
+#[derive(Resource, Debug, Default)]
+pub struct PathRequestQueue {
+    order: VecDeque<Entity>,
+    requests: HashMap<Entity, PathRequest>,
+    in_flight: HashMap<Entity, PathTask>,
+    snapshots: HashMap<AgentSize, NavMeshSnapshot>,
+    /// Copy of the `AreaMap`, shared with the path tasks.
+    areas: Option<Arc<AreaMap>>,
+}
+
+impl PathRequestQueue {
+    fn push(&mut self, event: &FindPathEvent, to_point: Vec3, size: AgentSize, costs: AreaCosts) {
+        let mut request = PathRequest {
+            entity: event.entity,
+            from_point: event.from_point,
+            to_point,
+            size,
+            costs,
+            attempts: 0,
+            last_failure: None,
+        };
+        // Requesters without a path keep asking every tick, so leave the task be
+        // unless it was asked for with positions that have gone stale.
+        if let Some(path_task) = self.in_flight.get(&event.entity) {
+            if path_task.request.matches(&request) {
+                return;
+            }
+            self.in_flight.remove(&event.entity);
+        }
+        // Still after the same path, so it keeps counting towards the retry limit.
+        if let Some(queued) = self
+            .requests
+            .get(&event.entity)
+            .filter(|queued| queued.matches(&request))
+        {
+            request.attempts = queued.attempts;
+            request.last_failure = queued.last_failure;
+        }
+        if self.requests.insert(event.entity, request).is_none() {
+            self.order.push_back(event.entity);
+        }
+    }
+
+    /// Dropping a task cancels it.
+    fn cancel(&mut self, entity: Entity) -> bool {
+        let queued = self.requests.remove(&entity).is_some();
+        let in_flight = self.in_flight.remove(&entity).is_some();
+        queued || in_flight
+    }
+
+    fn retry_or_fail(
+        &mut self,
+        commands: &mut Commands,
+        mut request: PathRequest,
+        generation: u32,
+        now: f32,
+    ) {
+        if request.attempts < PATH_REQUEST_CONFIG.max_attempts {
+            // A rebuild might be about to open a way through.
+            request.last_failure = Some((generation, now));
+            self.order.push_back(request.entity);
+            self.requests.insert(request.entity, request);
+        } else {
+            fail_path_request(commands, &request);
+        }
+    }
+}
+
 #[derive(Component, Debug, Reflect)]
 pub struct EntityPath {
     path: Vec<Vec3>,
@@ -75,42 +301,390 @@ impl EntityPath {
     pub fn new(path: Vec<Vec3>) -> Self {
         Self { path }
     }
+
+    /// The last waypoint of the path. Waypoints are stored in reverse order.
+    pub fn goal(&self) -> Option<Vec3> {
+        self.path.first().copied()
+    }
+
+    /// The waypoints still to come after the current destination, in order.
+    pub fn upcoming(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.path.iter().rev().copied()
+    }
 }
 
-pub fn find_path(
-    mut navmeshes: ResMut<Assets<NavMesh>>,
-    navmesh: Query<(&ManagedNavMesh, Ref<NavMeshStatus>)>,
+/// Queues every `FindPathEvent` until the requester's navmesh is ready to answer it.
+fn queue_path_requests(
+    mut commands: Commands,
+    mut queue: ResMut<PathRequestQueue>,
+    bounds: Res<WorldBounds>,
+    requesters: Query<(Option<&AgentSize>, Option<&AreaCosts>)>,
     mut find_path_event_reader: EventReader<FindPathEvent>,
+    mut cancel_path_event_reader: EventReader<CancelPathEvent>,
+) {
+    for event in find_path_event_reader.read() {
+        let Some(mut requester) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        requester.insert(PathRequestStatus::Pending);
+        let (size, costs) = requesters.get(event.entity).unwrap_or_default();
+        // Nothing gets to walk off the edge of the world, wherever it's running to.
+        queue.push(
+            event,
+            bounds.clamp(event.to_point),
+            size.copied().unwrap_or_default(),
+            costs.copied().unwrap_or_default(),
+        );
+    }
+    for event in cancel_path_event_reader.read() {
+        if queue.cancel(event.entity) {
+            if let Some(mut requester) = commands.get_entity(event.entity) {
+                requester.remove::<PathRequestStatus>();
+            }
+        }
+    }
+}
+
+/// Hands up to `max_per_tick` queued requests to the task pool, to be worked out
+/// against a snapshot of their size class's navmesh.
+fn dispatch_path_requests(
+    mut commands: Commands,
+    navmeshes: Res<Assets<NavMesh>>,
+    navmesh_query: Query<(&ManagedNavMesh, Ref<NavMeshStatus>, &AgentSize)>,
+    area_map: Res<AreaMap>,
+    mut queue: ResMut<PathRequestQueue>,
+    time: Res<Time>,
+) {
+    if area_map.is_changed() || queue.areas.is_none() {
+        queue.areas = Some(Arc::new(area_map.clone()));
+    }
+    let Some(areas) = queue.areas.clone() else {
+        return;
+    };
+
+    let mut building = Vec::new();
+    for (navmesh, status, size) in navmesh_query.iter() {
+        if *status != NavMeshStatus::Built {
+            // Requests for this size stay queued until the rebuild is done.
+            building.push(*size);
+            continue;
+        }
+        if status.is_changed() || !queue.snapshots.contains_key(size) {
+            let Some(navmesh) = navmeshes.get(navmesh) else {
+                continue;
+            };
+            let generation = queue.snapshots.get(size).map_or(1, |s| s.generation + 1);
+            queue.snapshots.insert(
+                *size,
+                NavMeshSnapshot {
+                    generation,
+                    navmesh: Arc::new(navmesh.clone()),
+                },
+            );
+        }
+    }
+
+    let task_pool = AsyncComputeTaskPool::get();
+    let now = time.elapsed_secs();
+    let mut budget = PATH_REQUEST_CONFIG.max_per_tick;
+    for _ in 0..queue.order.len() {
+        if budget == 0 {
+            break;
+        }
+        let Some(entity) = queue.order.pop_front() else {
+            break;
+        };
+        let Some(mut request) = queue.requests.remove(&entity) else {
+            continue;
+        };
+        // The requester may have been despawned since it asked.
+        if commands.get_entity(entity).is_none() {
+            continue;
+        }
+
+        let snapshot = queue
+            .snapshots
+            .get(&request.size)
+            .filter(|_| !building.contains(&request.size))
+            .map(|s| (s.generation, s.navmesh.clone()));
+        let Some((current_generation, navmesh)) = snapshot else {
+            queue.order.push_back(entity);
+            queue.requests.insert(entity, request);
+            continue;
+        };
+
+        if let Some((generation, failed_at)) = request.last_failure {
+            if generation == current_generation {
+                // Same navmesh as last time, so no point asking again yet.
+                if now - failed_at < PATH_REQUEST_CONFIG.retry_wait_secs {
+                    queue.order.push_back(entity);
+                    queue.requests.insert(entity, request);
+                } else {
+                    fail_path_request(&mut commands, &request);
+                }
+                continue;
+            }
+        }
+
+        budget -= 1;
+        request.attempts += 1;
+        let (from_point, to_point, costs) = (request.from_point, request.to_point, request.costs);
+        let areas = areas.clone();
+        let task = task_pool
+            .spawn(async move { cheapest_path(&navmesh, &areas, &costs, from_point, to_point) });
+        queue.in_flight.insert(
+            entity,
+            PathTask {
+                request,
+                generation: current_generation,
+                task,
+            },
+        );
+    }
+}
+
+/// Turns finished path tasks into `EntityPath`s, or queues them up for a retry.
+fn apply_path_results(
     mut commands: Commands,
+    mut queue: ResMut<PathRequestQueue>,
+    time: Res<Time>,
 ) {
-    let (navmesh, status) = navmesh.single();
-    if *status != NavMeshStatus::Built {
-        // TODO: probably need a more robust way to handle this
-        // println!("NavMesh is not ready yet. {:?}", status);
+    let now = time.elapsed_secs();
+    let mut finished = Vec::new();
+    for (entity, path_task) in queue.in_flight.iter_mut() {
+        if let Some(path) = block_on(future::poll_once(&mut path_task.task)) {
+            finished.push((*entity, path));
+        }
+    }
+
+    for (entity, path) in finished {
+        let Some(path_task) = queue.in_flight.remove(&entity) else {
+            continue;
+        };
+        let Some(mut requester) = commands.get_entity(entity) else {
+            continue;
+        };
+        if let Some(mut path) = path {
+            path.reverse();
+            requester.insert((EntityPath::new(path), PathRequestStatus::Found));
+            commands.trigger_targets(PathFoundEvent, entity);
+        } else {
+            queue.retry_or_fail(&mut commands, path_task.request, path_task.generation, now);
+        }
+    }
+}
+
+/// The navmesh's shortest path is the cheapest one unless it cuts through areas the
+/// agent would rather avoid, so try a search over the `AreaMap` as well and keep
+/// whichever costs less.
+fn cheapest_path(
+    navmesh: &NavMesh,
+    areas: &AreaMap,
+    costs: &AreaCosts,
+    from: Vec3,
+    to: Vec3,
+) -> Option<Vec<Vec3>> {
+    let shortest = navmesh.transformed_path(from, to).map(|path| {
+        let cost = areas.path_cost(costs, from, &path.path);
+        (path.path, cost)
+    });
+    let weighted = areas.cheapest_path(costs, from, to, |point| {
+        navmesh.transformed_is_in_mesh(point)
+    });
+
+    [shortest, weighted]
+        .into_iter()
+        .flatten()
+        .filter(|(_, cost)| cost.is_finite())
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(path, _)| path)
+}
+
+fn fail_path_request(commands: &mut Commands, request: &PathRequest) {
+    info!(
+        "no path found from {:?} to {:?}",
+        request.from_point, request.to_point
+    );
+    commands
+        .entity(request.entity)
+        .insert(PathRequestStatus::Failed);
+    commands.trigger_targets(NoPathFoundEvent, request.entity);
+}
+
+fn forget_despawned_path_request(
+    trigger: Trigger<Despawning>,
+    mut queue: ResMut<PathRequestQueue>,
+) {
+    queue.cancel(trigger.entity());
+}
+
+/// After every rebuild, checks the rest of each route against the new navmesh and
+/// asks for a new path to the same goal if something now blocks it.
+fn revalidate_paths(
+    mut commands: Commands,
+    queue: Res<PathRequestQueue>,
+    mut validated_generations: Local<HashMap<AgentSize, u32>>,
+    query: Query<(
+        Entity,
+        &Transform,
+        &EntityPath,
+        Option<&Destination>,
+        Option<&AgentSize>,
+    )>,
+    mut find_path_event_writer: EventWriter<FindPathEvent>,
+) {
+    let mut rebuilt = HashMap::new();
+    for (size, snapshot) in queue.snapshots.iter() {
+        if validated_generations.insert(*size, snapshot.generation) != Some(snapshot.generation) {
+            rebuilt.insert(*size, snapshot.navmesh.clone());
+        }
+    }
+    if rebuilt.is_empty() {
         return;
     }
-    // println!("NavMesh {:?}", status);
-    if let Some(navmesh) = navmeshes.get_mut(navmesh) {
-        for path_request in find_path_event_reader.read() {
-            if let Some(mut path) =
-                navmesh.transformed_path(path_request.from_point, path_request.to_point)
-            {
-                // info!(
-                //     "found path from {:?} to {:?}: {:?}",
-                //     path_request.from_point, path_request.to_point, path
-                // );
-                path.path.reverse();
-                commands
-                    .entity(path_request.entity)
-                    .insert(EntityPath::new(path.path));
-            } else {
-                info!(
-                    "no path found from {:?} to {:?}",
-                    path_request.from_point, path_request.to_point
-                );
-                commands.trigger_targets(NoPathFoundEvent, path_request.entity);
+
+    for (entity, transform, path, destination, size) in query.iter() {
+        let Some(navmesh) = rebuilt.get(&size.copied().unwrap_or_default()) else {
+            continue;
+        };
+        // Waypoints are stored in reverse, so walk them back to front.
+        let route = destination
+            .map(Destination::value)
+            .into_iter()
+            .chain(path.path.iter().rev().copied());
+        let mut from = transform.translation;
+        let mut blocked = false;
+        for to in route {
+            if !segment_in_mesh(navmesh.as_ref(), from, to) {
+                blocked = true;
+                break;
             }
+            from = to;
+        }
+        if !blocked {
+            continue;
+        }
+
+        let Some(goal) = path.goal().or(destination.map(Destination::value)) else {
+            continue;
+        };
+        debug!("{:?}'s path is blocked, re-pathing", entity);
+        commands
+            .entity(entity)
+            .remove::<(EntityPath, Destination)>();
+        find_path_event_writer.send(FindPathEvent::new(transform.translation, goal, entity));
+    }
+}
+
+/// Whether every point along the segment, not counting its start, is on the navmesh.
+fn segment_in_mesh(navmesh: &NavMesh, from: Vec3, to: Vec3) -> bool {
+    let steps = (from.distance(to) / PATH_VALIDATION_STEP).ceil().max(1.) as u32;
+    (1..=steps)
+        .all(|step| navmesh.transformed_is_in_mesh(from.lerp(to, step as f32 / steps as f32)))
+}
+
+/// Where an agent was when it last made progress towards its `Destination`.
+#[derive(Component, Debug)]
+pub struct Progress {
+    anchor: Vec3,
+    since_sim_secs: f64,
+    ticks: u32,
+}
+
+impl Progress {
+    fn new(anchor: Vec3, now: f64) -> Self {
+        Self {
+            anchor,
+            since_sim_secs: now,
+            ticks: 0,
+        }
+    }
+}
+
+/// Agents that haven't moved `min_progress` in `check_sim_seconds` and `min_ticks`
+/// while following a path drop it and ask for a new one to the same goal. Anything
+/// steering straight at a `Destination` without a path, like a chase, is left alone.
+fn detect_stuck(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    mut query: Query<(
+        Entity,
+        &Transform,
+        Ref<Destination>,
+        Ref<EntityPath>,
+        Option<&mut Progress>,
+    )>,
+    mut find_path_event_writer: EventWriter<FindPathEvent>,
+) {
+    let now = time_controller.simulated_elapsed_secs();
+    for (entity, transform, destination, path, progress) in query.iter_mut() {
+        let Some(mut progress) = progress else {
+            commands
+                .entity(entity)
+                .insert(Progress::new(transform.translation, now));
+            continue;
+        };
+        if destination.is_added() || path.is_added() {
+            *progress = Progress::new(transform.translation, now);
+            continue;
         }
+        progress.ticks += 1;
+        if now - progress.since_sim_secs < STUCK_CONFIG.check_sim_seconds
+            || progress.ticks < STUCK_CONFIG.min_ticks
+        {
+            continue;
+        }
+
+        if transform.translation.distance(progress.anchor) < STUCK_CONFIG.min_progress {
+            let goal = path.goal().unwrap_or(destination.value());
+            debug!("{:?} is stuck, re-pathing", entity);
+            commands
+                .entity(entity)
+                .remove::<(EntityPath, Destination, Progress)>();
+            find_path_event_writer.send(FindPathEvent::new(transform.translation, goal, entity));
+        } else {
+            *progress = Progress::new(transform.translation, now);
+        }
+    }
+}
+
+/// Paths can't start off the navmesh, e.g. when an obstacle appeared on top of the
+/// agent, so when one fails from there step back onto the closest free spot.
+pub fn return_to_navmesh(
+    trigger: Trigger<NoPathFoundEvent>,
+    mut commands: Commands,
+    navmeshes: NavMeshes,
+    query: Query<(&Transform, Option<&AgentSize>), Without<Destination>>,
+) {
+    let Ok((transform, size)) = query.get(trigger.entity()) else {
+        return;
+    };
+    let Some(navmesh) = navmeshes.get(size.copied().unwrap_or_default()) else {
+        return;
+    };
+    let position = transform.translation;
+    if navmesh.transformed_is_in_mesh(position) {
+        return;
+    }
+
+    let free_spot = STUCK_CONFIG
+        .nudge_radii
+        .iter()
+        .flat_map(|radius| {
+            (0..STUCK_CONFIG.nudge_directions).map(move |i| {
+                let angle = std::f32::consts::TAU * i as f32 / STUCK_CONFIG.nudge_directions as f32;
+                position + Quat::from_rotation_y(angle) * Vec3::X * *radius
+            })
+        })
+        .find(|point| navmesh.transformed_is_in_mesh(*point));
+    if let Some(free_spot) = free_spot {
+        debug!(
+            "{:?} is off the navmesh, stepping back on",
+            trigger.entity()
+        );
+        commands
+            .entity(trigger.entity())
+            .insert(Destination::new(free_spot));
     }
 }
 
@@ -126,3 +700,25 @@ pub fn traverse_path(
         }
     }
 }
+
+struct PathRequestConfig {
+    /// Most paths worked out in a single tick.
+    max_per_tick: u32,
+    max_attempts: u32,
+    /// How long a failed request waits for a rebuild before giving up.
+    retry_wait_secs: f32,
+    /// How far the ends of a path can move and it still count as the same request.
+    same_request_tolerance: f32,
+}
+
+struct StuckConfig {
+    check_sim_seconds: f64,
+    /// At high time scales a single tick covers the whole `check_sim_seconds`, so
+    /// agents also get at least this many ticks to make progress.
+    min_ticks: u32,
+    min_progress: f32,
+    /// Distances and number of directions tried when looking for a way back onto
+    /// the navmesh.
+    nudge_radii: &'static [f32],
+    nudge_directions: u32,
+}
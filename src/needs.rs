@@ -5,13 +5,29 @@ use big_brain::{
     scorers::Score,
     thinker::{ActionSpan, Actor, HasThinker, ScorerSpan},
 };
+use rand::Rng;
 
 use crate::{
-    navigation::{EntityPath, FindPathEvent},
+    despawn::Despawning,
+    navigation::{CancelPathEvent, EntityPath, FindPathEvent, NoPathFoundEvent},
+    personality::Personality,
+    spatial::SpatialIndex,
     time_control::{self, TimeController},
-    vegetation::Fruit,
+    utility::ResponseCurve,
+    vegetation::{Canopies, Fruit, Orchard, Ripeness},
 };
 
+/// Below this satiety an animal is starving and hurries to food.
+pub const CRITICAL_SATIETY: f32 = 10.0;
+/// How far an animal of average boldness will go for food, unless it's starving.
+const FORAGE_RANGE: f32 = 25.0;
+/// How many of the nearest targets `MoveToNearest` weighs up.
+const FORAGE_CANDIDATES: usize = 16;
+/// How close food has to be to eat it.
+const EAT_REACH: f32 = 1.5;
+/// How far from a tree's trunk an animal stands to browse it.
+const BROWSE_DISTANCE: f32 = 1.0;
+
 pub struct NeedsPlugin;
 
 impl Plugin for NeedsPlugin {
@@ -20,14 +36,20 @@ impl Plugin for NeedsPlugin {
             .insert_resource(NeedsTimer {
                 timer: Timer::from_seconds(60., TimerMode::Repeating),
             })
+            .add_observer(cancel_move_to_despawned::<Fruit>)
+            .add_observer(give_up_unreachable_browse)
             .add_systems(FixedUpdate, drain_needs)
             .add_plugins(BigBrainPlugin::new(PreUpdate))
             .add_systems(
                 PreUpdate,
                 (
-                    (eat_action_system, move_to_nearest_system::<Fruit>)
+                    (
+                        eat_action_system,
+                        browse_action_system,
+                        move_to_nearest_system::<Fruit>,
+                    )
                         .in_set(BigBrainSet::Actions),
-                    satiety_scorer_system.in_set(BigBrainSet::Scorers),
+                    (satiety_scorer_system, can_browse_scorer_system).in_set(BigBrainSet::Scorers),
                 ),
             );
     }
@@ -76,6 +98,15 @@ fn drain_needs(mut query: Query<&mut Satiety>, time_controller: Res<TimeControll
     }
 }
 
+/// How keen animals are to go for something, from 0 (not at all) to 1. Anything
+/// without one is as attractive as it gets.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Attractiveness(pub f32);
+
+/// How high off the ground an animal can reach to eat from trees, before its scale.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct BrowseHeight(pub f32);
+
 #[derive(Clone, Component, Debug, ActionBuilder)]
 pub struct Eat {
     until: f32,
@@ -93,23 +124,39 @@ impl Eat {
 pub struct MoveToNearest<T: Component + std::fmt::Debug + Clone> {
     // We use a PhantomData to store the type of the component we're moving to.
     _marker: std::marker::PhantomData<T>,
+    target: Option<Entity>,
 }
 
 impl<T: Component + std::fmt::Debug + Clone> MoveToNearest<T> {
     pub fn new() -> Self {
         Self {
             _marker: std::marker::PhantomData,
+            target: None,
         }
     }
 }
 
+/// Needs a `SpatialIndexPlugin::<T>` to find targets with.
 pub fn move_to_nearest_system<T: Component + std::fmt::Debug + Clone>(
-    mut query: Query<&mut Transform, With<T>>,
-    thinkers: Query<(&Transform, Has<EntityPath>, Entity), (With<HasThinker>, Without<T>)>,
-    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<MoveToNearest<T>>>,
+    index: Res<SpatialIndex<T>>,
+    query: Query<(Entity, &Transform, Option<&Attractiveness>), With<T>>,
+    thinkers: Query<
+        (
+            &Transform,
+            Has<EntityPath>,
+            Entity,
+            Option<&Personality>,
+            Option<&Satiety>,
+        ),
+        (With<HasThinker>, Without<T>),
+    >,
+    mut action_query: Query<(&Actor, &mut ActionState, &mut MoveToNearest<T>, &ActionSpan)>,
     mut find_path_event_writer: EventWriter<FindPathEvent>,
+    mut cancel_path_event_writer: EventWriter<CancelPathEvent>,
 ) {
-    for (actor, mut action_state, span) in &mut action_query {
+    let mut rng = rand::rng();
+
+    for (actor, mut action_state, mut move_to, span) in &mut action_query {
         let _guard = span.span().enter();
 
         match *action_state {
@@ -119,23 +166,63 @@ pub fn move_to_nearest_system<T: Component + std::fmt::Debug + Clone>(
                 *action_state = ActionState::Executing;
             }
             ActionState::Executing => {
-                let (actor_transform, has_path, entity) = thinkers.get(actor.0).unwrap();
-                // The goal is the nearest entity with the specified component.
-                let goal_transform = query
-                    .iter_mut()
-                    .map(|t| (t.translation, t))
-                    .min_by(|(a, _), (b, _)| {
-                        let dist_a = actor_transform.translation.distance_squared(*a);
-                        let dist_b = actor_transform.translation.distance_squared(*b);
-                        dist_a.partial_cmp(&dist_b).unwrap()
-                    })
-                    .and_then(|t| Some(t.1));
-                let Some(goal_transform) = goal_transform else {
-                    continue;
+                let (actor_transform, has_path, entity, personality, satiety) =
+                    thinkers.get(actor.0).unwrap();
+                let personality = personality.copied().unwrap_or_default();
+
+                // Stick with the target we picked as long as it's still around and
+                // worth going for.
+                let goal = move_to
+                    .target
+                    .and_then(|target| query.get(target).ok())
+                    .filter(|(_, _, attractiveness)| attractiveness.is_none_or(|a| a.0 > 0.));
+                let goal = match goal {
+                    Some((_, goal_transform, _)) => goal_transform.translation,
+                    None => {
+                        // Further is fine for something more attractive.
+                        let candidates: Vec<(Entity, f32, f32)> = index
+                            .nearest_k(actor_transform.translation, FORAGE_CANDIDATES)
+                            .into_iter()
+                            .filter_map(|(e, position)| {
+                                let attractiveness = query.get(e).ok()?.2.map_or(1., |a| a.0);
+                                let distance = actor_transform.translation.distance(position);
+                                (attractiveness > 0.).then_some((
+                                    e,
+                                    distance,
+                                    distance / attractiveness,
+                                ))
+                            })
+                            .collect();
+                        let Some(&(_, nearest, best)) =
+                            candidates.iter().min_by(|a, b| a.2.total_cmp(&b.2))
+                        else {
+                            continue;
+                        };
+
+                        let range = FORAGE_RANGE * personality.forage_range_weight();
+                        let starving = satiety.is_some_and(|s| s.value < CRITICAL_SATIETY);
+                        if nearest > range && !starving {
+                            debug!("Nothing close enough to bother with");
+                            *action_state = ActionState::Failure;
+                            continue;
+                        }
+
+                        // Anything about as good as the best one will do.
+                        let slack = personality.tie_break_slack();
+                        let good_enough: Vec<Entity> = candidates
+                            .iter()
+                            .filter(|(_, _, weighted)| *weighted <= best * slack)
+                            .map(|(e, _, _)| *e)
+                            .collect();
+                        if good_enough.is_empty() {
+                            continue;
+                        }
+                        let target = good_enough[rng.random_range(0..good_enough.len())];
+                        move_to.target = Some(target);
+                        query.get(target).unwrap().1.translation
+                    }
                 };
-                let distance = actor_transform
-                    .translation
-                    .distance_squared(goal_transform.translation);
+                let distance = actor_transform.translation.distance_squared(goal);
 
                 trace!("Distance: {}", distance);
 
@@ -146,7 +233,7 @@ pub fn move_to_nearest_system<T: Component + std::fmt::Debug + Clone>(
                     if !has_path {
                         find_path_event_writer.send(FindPathEvent::new(
                             actor_transform.translation,
-                            goal_transform.translation,
+                            goal,
                             entity,
                         ));
                     }
@@ -157,6 +244,7 @@ pub fn move_to_nearest_system<T: Component + std::fmt::Debug + Clone>(
                 }
             }
             ActionState::Cancelled => {
+                cancel_path_event_writer.send(CancelPathEvent::new(actor.0));
                 *action_state = ActionState::Failure;
             }
             _ => {}
@@ -164,23 +252,51 @@ pub fn move_to_nearest_system<T: Component + std::fmt::Debug + Clone>(
     }
 }
 
+/// Cancels any `MoveToNearest<T>` heading for an entity that's going away.
+fn cancel_move_to_despawned<T: Component + std::fmt::Debug + Clone>(
+    trigger: Trigger<Despawning>,
+    mut action_query: Query<(&mut ActionState, &MoveToNearest<T>)>,
+) {
+    for (mut state, move_to) in action_query.iter_mut() {
+        if move_to.target == Some(trigger.entity()) && *state == ActionState::Executing {
+            *state = ActionState::Cancelled;
+        }
+    }
+}
+
 fn eat_action_system(
     time_controller: Res<TimeController>,
-    mut satieties: Query<&mut Satiety>,
+    fruit_index: Res<SpatialIndex<Fruit>>,
+    fruit: Query<&Ripeness, With<Fruit>>,
+    mut satieties: Query<(&Transform, &mut Satiety)>,
     mut query: Query<(&Actor, &mut ActionState, &Eat, &ActionSpan)>,
 ) {
     for (Actor(actor), mut state, eat, span) in &mut query {
         let _guard = span.span().enter();
 
-        if let Ok(mut satiety) = satieties.get_mut(*actor) {
+        if let Ok((transform, mut satiety)) = satieties.get_mut(*actor) {
             match *state {
                 ActionState::Requested => {
                     debug!("Time to eat!");
                     *state = ActionState::Executing;
                 }
                 ActionState::Executing => {
+                    // Whatever's best within reach.
+                    let Some(nutrition) = fruit_index
+                        .within_radius(transform.translation, EAT_REACH)
+                        .into_iter()
+                        .filter_map(|(e, _)| fruit.get(e).ok())
+                        .map(|ripeness| ripeness.nutrition())
+                        .max_by(f32::total_cmp)
+                    else {
+                        debug!("Nothing left to eat");
+                        *state = ActionState::Failure;
+                        continue;
+                    };
+
                     trace!("EAting...");
-                    satiety.value += eat.per_second * time_controller.scaled_delta().as_secs_f32();
+                    satiety.value +=
+                        eat.per_second * nutrition * time_controller.scaled_delta().as_secs_f32();
                     satiety.value = satiety.value.min(90.);
                     if satiety.value >= eat.until {
                         debug!("Done eating");
@@ -197,16 +313,148 @@ fn eat_action_system(
     }
 }
 
+/// Walks up to a tree and eats fruit straight off it, if any hangs low enough.
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Browse {
+    until: f32,
+    per_second: f32,
+    target: Option<Entity>,
+    /// Whether it's started down the path to the tree.
+    on_the_way: bool,
+}
+
+impl Browse {
+    pub fn new(until: f32, per_second: f32) -> Self {
+        Self {
+            until,
+            per_second,
+            target: None,
+            on_the_way: false,
+        }
+    }
+}
+
+fn browse_action_system(
+    time_controller: Res<TimeController>,
+    canopies: Canopies,
+    mut browsers: Query<(
+        &Transform,
+        &mut Satiety,
+        &BrowseHeight,
+        Option<&Personality>,
+        Has<EntityPath>,
+    )>,
+    mut query: Query<(&Actor, &mut ActionState, &mut Browse, &ActionSpan)>,
+    mut find_path_event_writer: EventWriter<FindPathEvent>,
+    mut cancel_path_event_writer: EventWriter<CancelPathEvent>,
+    mut orchard: Orchard,
+) {
+    for (Actor(actor), mut state, mut browse, span) in &mut query {
+        let _guard = span.span().enter();
+        let Ok((transform, mut satiety, browse_height, personality, has_path)) =
+            browsers.get_mut(*actor)
+        else {
+            continue;
+        };
+
+        match *state {
+            ActionState::Requested => {
+                let range = FORAGE_RANGE * personality.map_or(1., |p| p.forage_range_weight());
+                let reach = browse_height.0 * transform.scale.y;
+                let reachable = canopies.reachable(transform.translation, reach, range);
+                browse.target = reachable.map(|(fruit, _)| fruit);
+                browse.on_the_way = false;
+                if let Some((fruit, tree)) = reachable {
+                    debug!("Going to browse {:?}", fruit);
+                    let from_trunk = (transform.translation - tree).with_y(0.);
+                    if from_trunk.length() > 2. * BROWSE_DISTANCE {
+                        let spot = tree + from_trunk.normalize_or(Vec3::X) * BROWSE_DISTANCE;
+                        find_path_event_writer.send(FindPathEvent::new(
+                            transform.translation,
+                            spot,
+                            *actor,
+                        ));
+                    }
+                    *state = ActionState::Executing;
+                } else {
+                    debug!("Nothing hanging low enough");
+                    *state = ActionState::Failure;
+                }
+            }
+            ActionState::Executing => {
+                // It may have fallen, or someone else got to it.
+                let Some((fruit, (tree, ripeness))) = browse
+                    .target
+                    .and_then(|fruit| Some((fruit, canopies.hanging(fruit)?)))
+                else {
+                    debug!("The fruit's gone");
+                    cancel_path_event_writer.send(CancelPathEvent::new(*actor));
+                    *state = ActionState::Failure;
+                    continue;
+                };
+
+                let from_trunk = (transform.translation - tree).with_y(0.);
+                if from_trunk.length() > 2. * BROWSE_DISTANCE {
+                    if has_path {
+                        browse.on_the_way = true;
+                    } else if browse.on_the_way {
+                        debug!("Couldn't get close enough to browse");
+                        *state = ActionState::Failure;
+                    }
+                    continue;
+                }
+
+                trace!("Browsing...");
+                satiety.value += browse.per_second
+                    * ripeness.nutrition()
+                    * time_controller.scaled_delta().as_secs_f32();
+                satiety.value = satiety.value.min(90.);
+                if satiety.value >= browse.until {
+                    debug!("Done browsing");
+                    orchard.pick(fruit);
+                    *state = ActionState::Success;
+                }
+            }
+            ActionState::Cancelled => {
+                cancel_path_event_writer.send(CancelPathEvent::new(*actor));
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gives up browsing a tree there's no way to get to.
+fn give_up_unreachable_browse(
+    trigger: Trigger<NoPathFoundEvent>,
+    mut action_query: Query<(&Actor, &mut ActionState), With<Browse>>,
+) {
+    for (Actor(actor), mut state) in action_query.iter_mut() {
+        if *actor == trigger.entity() && *state == ActionState::Executing {
+            debug!("No way to get to the tree");
+            *state = ActionState::Failure;
+        }
+    }
+}
+
 #[derive(Clone, Component, Debug, ScorerBuilder)]
-pub struct Hungry;
+pub struct Hungry {
+    curve: ResponseCurve,
+}
+
+impl Hungry {
+    pub fn new(curve: ResponseCurve) -> Self {
+        Self { curve }
+    }
+}
 
 pub fn satiety_scorer_system(
-    satieties: Query<&Satiety>,
-    mut query: Query<(&Actor, &mut Score, &ScorerSpan), With<Hungry>>,
+    satieties: Query<(&Satiety, Option<&Personality>)>,
+    mut query: Query<(&Actor, &mut Score, &Hungry, &ScorerSpan)>,
 ) {
-    for (Actor(actor), mut score, span) in &mut query {
-        if let Ok(satiety) = satieties.get(*actor) {
-            let hunger = (100.0 - satiety.value).max(0.0) / 100.0;
+    for (Actor(actor), mut score, hungry, span) in &mut query {
+        if let Ok((satiety, personality)) = satieties.get(*actor) {
+            let hunger = hunger_score(&hungry.curve, satiety, personality);
             // println!("hunger score {}", hunger);
             score.set(hunger);
             if satiety.value <= 20.0 {
@@ -216,3 +464,48 @@ pub fn satiety_scorer_system(
         }
     }
 }
+
+/// Scores like `Hungry`, but only while there's fruit hanging within reach.
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct CanBrowse {
+    curve: ResponseCurve,
+}
+
+impl CanBrowse {
+    pub fn new(curve: ResponseCurve) -> Self {
+        Self { curve }
+    }
+}
+
+pub fn can_browse_scorer_system(
+    canopies: Canopies,
+    browsers: Query<(&Transform, &Satiety, &BrowseHeight, Option<&Personality>)>,
+    mut query: Query<(&Actor, &mut Score, &CanBrowse)>,
+) {
+    for (Actor(actor), mut score, can_browse) in &mut query {
+        let Ok((transform, satiety, browse_height, personality)) = browsers.get(*actor) else {
+            score.set(0.);
+            continue;
+        };
+        let range = FORAGE_RANGE * personality.map_or(1., |p| p.forage_range_weight());
+        let reach = browse_height.0 * transform.scale.y;
+        if canopies
+            .reachable(transform.translation, reach, range)
+            .is_some()
+        {
+            score.set(hunger_score(&can_browse.curve, satiety, personality));
+        } else {
+            score.set(0.);
+        }
+    }
+}
+
+fn hunger_score(
+    curve: &ResponseCurve,
+    satiety: &Satiety,
+    personality: Option<&Personality>,
+) -> f32 {
+    let hunger = (100.0 - satiety.value).max(0.0) / 100.0;
+    let weight = personality.map_or(1., |p| p.hunger_weight());
+    (curve.evaluate(hunger) * weight).min(1.)
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+pub struct PersonalityPlugin;
+
+impl Plugin for PersonalityPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Personality>();
+    }
+}
+
+/// Per-individual biases on top of the species behavior. Each trait runs from 0 to 1,
+/// with 0.5 being average.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Personality {
+    /// Bold animals forage farther from where they are and flee later.
+    pub boldness: f32,
+    /// Sociable animals flee towards others of their kind.
+    pub sociability: f32,
+    /// Curious animals don't always go for the closest target.
+    pub curiosity: f32,
+    /// Greedy animals get hungry sooner.
+    pub greed: f32,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Self {
+            boldness: 0.5,
+            sociability: 0.5,
+            curiosity: 0.5,
+            greed: 0.5,
+        }
+    }
+}
+
+impl Personality {
+    pub fn random() -> Self {
+        let mut rng = rand::rng();
+        Self {
+            boldness: rng.random(),
+            sociability: rng.random(),
+            curiosity: rng.random(),
+            greed: rng.random(),
+        }
+    }
+
+    /// Multiplies the `Scared` score, so shy animals flee sooner.
+    pub fn fear_weight(&self) -> f32 {
+        1.5 - self.boldness
+    }
+
+    /// Multiplies the `Hungry` score.
+    pub fn hunger_weight(&self) -> f32 {
+        0.5 + self.greed
+    }
+
+    /// Multiplies how far away the animal is willing to go for food.
+    pub fn forage_range_weight(&self) -> f32 {
+        0.5 + self.boldness
+    }
+
+    /// Targets up to this many times further than the nearest one are just as good.
+    pub fn tie_break_slack(&self) -> f32 {
+        1. + self.curiosity
+    }
+}
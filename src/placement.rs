@@ -0,0 +1,214 @@
+use std::fmt;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+
+use crate::{
+    avoidance::AvoidanceAgent,
+    biome::{BiomeMap, BiomeWeights},
+    bounds::WorldBounds,
+    controller::CollisionLayer,
+    navigation::{AgentSize, NavMeshes},
+    spatial::SpatialIndex,
+    terrain::Terrain,
+    vegetation::{Fruit, Tree},
+};
+
+const PLACEMENT_CONFIG: PlacementConfig = PlacementConfig {
+    max_attempts: 32,
+    collider_check_height: 2.,
+};
+
+/// Things placement can keep its distance from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlacementKind {
+    Tree,
+    Fruit,
+    Animal,
+}
+
+/// Where to look for a spot.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchArea {
+    /// Anywhere at least `margin` in from the edge of the world.
+    Anywhere { margin: f32 },
+    /// As close to `center` as possible, but no further out than `radius`. With a
+    /// radius of zero only `center` itself is tried.
+    Around { center: Vec3, radius: f32 },
+}
+
+/// What has to hold for a spot to be valid. Staying inside the world bounds
+/// always has to.
+#[derive(Clone, Debug, Default)]
+pub struct PlacementConstraints {
+    on_navmesh: Option<AgentSize>,
+    clear_of_colliders: bool,
+    min_distances: Vec<(PlacementKind, f32)>,
+    biomes: Option<BiomeWeights>,
+}
+
+impl PlacementConstraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Somewhere agents of this size can walk.
+    pub fn on_navmesh(mut self, size: AgentSize) -> Self {
+        self.on_navmesh = Some(size);
+        self
+    }
+
+    /// Not overlapping any collider.
+    pub fn clear_of_colliders(mut self) -> Self {
+        self.clear_of_colliders = true;
+        self
+    }
+
+    /// More likely in the biomes with higher weights, never in those weighted 0.
+    pub fn preferring(mut self, biomes: BiomeWeights) -> Self {
+        self.biomes = Some(biomes);
+        self
+    }
+
+    /// At least `distance` from every thing of the given kind.
+    pub fn min_distance_from(mut self, kind: PlacementKind, distance: f32) -> Self {
+        self.min_distances.push((kind, distance));
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlacementError {
+    /// The navmesh this has to go on hasn't been built yet. Worth trying again later.
+    NavMeshNotReady(AgentSize),
+    /// Nowhere in the search area satisfied every constraint.
+    NoValidSpot,
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::NavMeshNotReady(size) => {
+                write!(f, "the {:?} navmesh isn't ready yet", size)
+            }
+            PlacementError::NoValidSpot => write!(f, "no spot satisfies the constraints"),
+        }
+    }
+}
+
+/// Finds spots to put new things on the ground.
+#[derive(SystemParam)]
+pub struct Placement<'w, 's> {
+    bounds: Res<'w, WorldBounds>,
+    terrain: Res<'w, Terrain>,
+    biome_map: Res<'w, BiomeMap>,
+    navmeshes: NavMeshes<'w, 's>,
+    spatial_query: SpatialQuery<'w, 's>,
+    tree_index: ResMut<'w, SpatialIndex<Tree>>,
+    fruit_index: ResMut<'w, SpatialIndex<Fruit>>,
+    animal_index: ResMut<'w, SpatialIndex<AvoidanceAgent>>,
+}
+
+impl Placement<'_, '_> {
+    /// Finds a spot on the ground for something taking up a circle of radius `footprint`.
+    /// The spot is at ground height.
+    pub fn place(
+        &self,
+        footprint: f32,
+        area: SearchArea,
+        constraints: &PlacementConstraints,
+    ) -> Result<Vec3, PlacementError> {
+        let navmesh = match constraints.on_navmesh {
+            Some(size) => Some(
+                self.navmeshes
+                    .get(size)
+                    .ok_or(PlacementError::NavMeshNotReady(size))?,
+            ),
+            None => None,
+        };
+
+        let mut rng = rand::rng();
+        let attempts = match area {
+            SearchArea::Around { radius, .. } if radius <= 0. => 1,
+            _ => PLACEMENT_CONFIG.max_attempts,
+        };
+        for attempt in 0..attempts {
+            let point = match area {
+                SearchArea::Anywhere { margin } => {
+                    self.bounds.random_point(&mut rng, margin + footprint)
+                }
+                // Try the spot itself first, then spread out.
+                SearchArea::Around { center, .. } if attempt == 0 => center.with_y(0.),
+                SearchArea::Around { center, radius } => {
+                    let spread = radius * attempt as f32 / attempts as f32;
+                    let offset = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU))
+                        * rng.random_range(0.0..=spread);
+                    center.with_y(0.) + Vec3::new(offset.x, 0., offset.y)
+                }
+            };
+
+            let valid = self.inside_bounds(point, footprint)
+                && navmesh.is_none_or(|navmesh| navmesh.transformed_is_in_mesh(point))
+                && (!constraints.clear_of_colliders || self.clear_of_colliders(point, footprint))
+                && constraints
+                    .min_distances
+                    .iter()
+                    .all(|(kind, distance)| self.far_enough_from(*kind, point, *distance));
+            // Less favoured biomes only get a spot some of the time.
+            let favoured = constraints.biomes.is_none_or(|biomes| {
+                let weight = biomes.weight(self.biome_map.biome_at(point));
+                rng.random_bool(weight.clamp(0., 1.) as f64)
+            });
+            if valid && favoured {
+                return Ok(point.with_y(self.terrain.height_at(point)));
+            }
+        }
+        Err(PlacementError::NoValidSpot)
+    }
+
+    /// Records something that's just been placed, so the rest of the batch keeps its
+    /// distance before the spatial indexes catch up at the end of the frame.
+    pub fn claim(&mut self, kind: PlacementKind, entity: Entity, point: Vec3) {
+        match kind {
+            PlacementKind::Tree => self.tree_index.insert(entity, point),
+            PlacementKind::Fruit => self.fruit_index.insert(entity, point),
+            PlacementKind::Animal => self.animal_index.insert(entity, point),
+        }
+    }
+
+    fn inside_bounds(&self, point: Vec3, footprint: f32) -> bool {
+        point.x.abs() + footprint <= self.bounds.half_extent
+            && point.z.abs() + footprint <= self.bounds.half_extent
+    }
+
+    /// Checks from the ground up at `point`, whatever its height.
+    fn clear_of_colliders(&self, point: Vec3, footprint: f32) -> bool {
+        let height = PLACEMENT_CONFIG.collider_check_height;
+        let point = point.with_y(self.terrain.height_at(point));
+        self.spatial_query
+            .shape_intersections(
+                &Collider::cylinder(footprint, height),
+                point + Vec3::Y * 0.5 * height,
+                Quat::IDENTITY,
+                // Everything's touching the ground.
+                &SpatialQueryFilter::from_mask(CollisionLayer::Default),
+            )
+            .is_empty()
+    }
+
+    fn far_enough_from(&self, kind: PlacementKind, point: Vec3, distance: f32) -> bool {
+        let nearest = match kind {
+            PlacementKind::Tree => self.tree_index.nearest_within(point, distance),
+            PlacementKind::Fruit => self.fruit_index.nearest_within(point, distance),
+            PlacementKind::Animal => self.animal_index.nearest_within(point, distance),
+        };
+        nearest.is_none()
+    }
+}
+
+struct PlacementConfig {
+    max_attempts: u32,
+    /// How tall a cylinder `clear_of_colliders` checks with.
+    collider_check_height: f32,
+}
@@ -0,0 +1,582 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use bevy::prelude::*;
+use big_brain::{
+    BigBrainSet,
+    prelude::{ActionBuilder, ActionState, ScorerBuilder},
+    scorers::Score,
+    thinker::{ActionSpan, Actor, ScorerSpan, ThinkerBuilder},
+};
+use rand::Rng;
+
+use crate::{
+    age::Age,
+    animal::Animal,
+    areas::AreaCosts,
+    asset_loader::AnimationData,
+    avoidance::AvoidanceAgent,
+    controller::CharacterController,
+    despawn::{DespawnCause, DespawnRequest, Despawning},
+    interpolation::Interpolated,
+    life_cycle::{DeathCause, DeathEvent, LifeCycle, LifeStage},
+    movement::{Destination, Speed, Stamina, Urgent},
+    navigation::{AgentSize, CancelPathEvent, EntityPath, FindPathEvent, Progress},
+    needs::{PhysicalNeeds, Satiety},
+    personality::Personality,
+    soil::SoilGrid,
+    spatial::{SpatialIndex, SpatialIndexPlugin},
+    time_control::TimeController,
+    utility::ResponseCurve,
+};
+
+const SPATIAL_CELL_SIZE: f32 = 8.0;
+
+const CARCASS_CONFIG: CarcassConfig = CarcassConfig {
+    meat: 150.0,
+    decay_check_sim_seconds: 60. * 60.,
+    lifespan_days: 3.,
+    nutrients_per_meat: 0.002,
+};
+
+pub struct PredationPlugin;
+
+impl Plugin for PredationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Carcass>()
+            .add_plugins(SpatialIndexPlugin::<Prey>::new(SPATIAL_CELL_SIZE))
+            .add_plugins(SpatialIndexPlugin::<Predator>::new(SPATIAL_CELL_SIZE))
+            .insert_resource(CarcassDecayTimer {
+                timer: Timer::from_seconds(
+                    CARCASS_CONFIG.decay_check_sim_seconds,
+                    TimerMode::Repeating,
+                ),
+            })
+            .add_observer(forget_despawned_quarry)
+            .add_systems(FixedUpdate, decay_carcasses)
+            .add_systems(
+                PreUpdate,
+                (
+                    (
+                        stalk_action_system,
+                        chase_action_system,
+                        kill_action_system,
+                        eat_carcass_action_system,
+                        flee_action_system,
+                    )
+                        .in_set(BigBrainSet::Actions),
+                    scared_scorer_system.in_set(BigBrainSet::Scorers),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct CarcassDecayTimer {
+    timer: Timer,
+}
+
+/// Hunts `Prey`.
+#[derive(Component, Debug, Default)]
+pub struct Predator;
+
+/// Hunted by `Predator`s, and runs away from them.
+#[derive(Component, Debug, Default)]
+pub struct Prey;
+
+/// The prey a predator is currently hunting, and later feeding on.
+#[derive(Component, Debug)]
+pub struct Quarry(pub Entity);
+
+/// What's left of a killed animal. Predators eat the meat until there is none left.
+#[derive(Component, Debug, Reflect)]
+pub struct Carcass {
+    pub meat: f32,
+}
+
+impl Carcass {
+    pub fn new(meat: f32) -> Self {
+        Self { meat }
+    }
+}
+
+fn decay_carcasses(
+    query: Query<(Entity, &Age, &Transform, &Carcass)>,
+    time_controller: Res<TimeController>,
+    mut soil_grid: ResMut<SoilGrid>,
+    mut decay_timer: ResMut<CarcassDecayTimer>,
+    mut despawn_request_writer: EventWriter<DespawnRequest>,
+) {
+    decay_timer.timer.tick(time_controller.scaled_delta());
+    if !decay_timer.timer.just_finished() {
+        return;
+    }
+
+    for (entity, age, transform, carcass) in query.iter() {
+        if age.age_days(&time_controller) as f32 > CARCASS_CONFIG.lifespan_days {
+            // Whatever wasn't eaten rots into the ground.
+            soil_grid.deposit(
+                transform.translation,
+                carcass.meat * CARCASS_CONFIG.nutrients_per_meat,
+            );
+            despawn_request_writer.send(DespawnRequest::new(entity, DespawnCause::Decayed));
+        }
+    }
+}
+
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Stalk {
+    search_radius: f32,
+    pounce_distance: f32,
+}
+
+impl Stalk {
+    pub fn new(search_radius: f32, pounce_distance: f32) -> Self {
+        Self {
+            search_radius,
+            pounce_distance,
+        }
+    }
+}
+
+fn stalk_action_system(
+    mut commands: Commands,
+    prey_index: Res<SpatialIndex<Prey>>,
+    prey_query: Query<&Transform, (With<Prey>, Without<Predator>)>,
+    predators: Query<(&Transform, Option<&Quarry>, Has<EntityPath>), With<Predator>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &Stalk, &ActionSpan)>,
+    mut find_path_event_writer: EventWriter<FindPathEvent>,
+    mut cancel_path_event_writer: EventWriter<CancelPathEvent>,
+) {
+    for (Actor(actor), mut state, stalk, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((transform, quarry, has_path)) = predators.get(*actor) else {
+            continue;
+        };
+
+        match *state {
+            ActionState::Requested => {
+                let nearest = prey_index.nearest_within(transform.translation, stalk.search_radius);
+
+                if let Some((prey, _)) = nearest {
+                    debug!("Stalking {:?}", prey);
+                    commands.entity(*actor).insert(Quarry(prey));
+                    *state = ActionState::Executing;
+                } else {
+                    debug!("Nothing to hunt around here");
+                    *state = ActionState::Failure;
+                }
+            }
+            ActionState::Executing => {
+                let Some(prey_transform) = quarry.and_then(|q| prey_query.get(q.0).ok()) else {
+                    debug!("Lost the quarry");
+                    commands.entity(*actor).remove::<Quarry>();
+                    *state = ActionState::Failure;
+                    continue;
+                };
+                let distance = transform
+                    .translation
+                    .distance_squared(prey_transform.translation);
+
+                if distance <= stalk.pounce_distance * stalk.pounce_distance {
+                    debug!("Close enough to pounce");
+                    *state = ActionState::Success;
+                } else if !has_path {
+                    find_path_event_writer.send(FindPathEvent::new(
+                        transform.translation,
+                        prey_transform.translation,
+                        *actor,
+                    ));
+                }
+            }
+            ActionState::Cancelled => {
+                commands.entity(*actor).remove::<Quarry>();
+                cancel_path_event_writer.send(CancelPathEvent::new(*actor));
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Chase {
+    kill_distance: f32,
+    give_up_distance: f32,
+}
+
+impl Chase {
+    pub fn new(kill_distance: f32, give_up_distance: f32) -> Self {
+        Self {
+            kill_distance,
+            give_up_distance,
+        }
+    }
+}
+
+fn chase_action_system(
+    mut commands: Commands,
+    prey_query: Query<&Transform, (With<Prey>, Without<Predator>)>,
+    predators: Query<(&Transform, Option<&Quarry>), With<Predator>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &Chase, &ActionSpan)>,
+) {
+    for (Actor(actor), mut state, chase, span) in &mut action_query {
+        let _guard = span.span().enter();
+        let Ok((transform, quarry)) = predators.get(*actor) else {
+            continue;
+        };
+
+        match *state {
+            ActionState::Requested => {
+                debug!("Chasing!");
+                // Chase in a straight line instead of following the stalking path.
+                commands
+                    .entity(*actor)
+                    .remove::<(EntityPath, Destination)>()
+                    .insert(Urgent);
+                *state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                let Some(prey_transform) = quarry.and_then(|q| prey_query.get(q.0).ok()) else {
+                    debug!("Lost the quarry");
+                    commands
+                        .entity(*actor)
+                        .remove::<(Urgent, Destination, Quarry)>();
+                    *state = ActionState::Failure;
+                    continue;
+                };
+                let distance = transform
+                    .translation
+                    .distance_squared(prey_transform.translation);
+
+                if distance <= chase.kill_distance * chase.kill_distance {
+                    commands.entity(*actor).remove::<(Urgent, Destination)>();
+                    *state = ActionState::Success;
+                } else if distance > chase.give_up_distance * chase.give_up_distance {
+                    debug!("It got away");
+                    commands
+                        .entity(*actor)
+                        .remove::<(Urgent, Destination, Quarry)>();
+                    *state = ActionState::Failure;
+                } else {
+                    commands
+                        .entity(*actor)
+                        .insert(Destination::new(prey_transform.translation));
+                }
+            }
+            ActionState::Cancelled => {
+                commands
+                    .entity(*actor)
+                    .remove::<(Urgent, Destination, Quarry)>();
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Kill;
+
+fn kill_action_system(
+    mut commands: Commands,
+    prey_query: Query<(), (With<Prey>, Without<Predator>)>,
+    predators: Query<&Quarry, With<Predator>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &ActionSpan), With<Kill>>,
+) {
+    for (Actor(actor), mut state, span) in &mut action_query {
+        let _guard = span.span().enter();
+
+        match *state {
+            ActionState::Requested => {
+                let Ok(Quarry(prey)) = predators.get(*actor) else {
+                    *state = ActionState::Failure;
+                    continue;
+                };
+                if !prey_query.contains(*prey) {
+                    commands.entity(*actor).remove::<Quarry>();
+                    *state = ActionState::Failure;
+                    continue;
+                }
+
+                debug!("Killed {:?}", prey);
+                commands.trigger_targets(
+                    DeathEvent {
+                        cause: DeathCause::Predation,
+                    },
+                    *prey,
+                );
+                *state = ActionState::Success;
+            }
+            ActionState::Cancelled => {
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Calls off the hunt, or the meal, of every predator whose quarry is going away.
+fn forget_despawned_quarry(
+    trigger: Trigger<Despawning>,
+    mut commands: Commands,
+    predators: Query<(Entity, &Quarry)>,
+    mut action_query: Query<
+        (&Actor, &mut ActionState),
+        Or<(With<Stalk>, With<Chase>, With<Kill>, With<EatCarcass>)>,
+    >,
+) {
+    let despawned = trigger.entity();
+    for (predator, Quarry(quarry)) in predators.iter() {
+        if *quarry != despawned {
+            continue;
+        }
+        commands.entity(predator).remove::<Quarry>();
+        for (Actor(actor), mut state) in action_query.iter_mut() {
+            if *actor == predator
+                && matches!(*state, ActionState::Requested | ActionState::Executing)
+            {
+                *state = ActionState::Cancelled;
+            }
+        }
+    }
+}
+
+/// Turns a dead animal into a `Carcass`, whatever it died of.
+pub fn become_carcass(
+    trigger: Trigger<DeathEvent>,
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    mut query: Query<(&mut Transform, &mut AnimationData), With<Animal>>,
+    mut cancel_path_event_writer: EventWriter<CancelPathEvent>,
+) {
+    let entity = trigger.entity();
+    let Ok((mut transform, mut anim)) = query.get_mut(entity) else {
+        return;
+    };
+
+    debug!("{:?} died of {:?}", entity, trigger.event().cause);
+    // Lay the body on its side.
+    transform.rotate_local_z(FRAC_PI_2);
+    anim.animation_index = 0;
+    anim.animation_speed = 1.0;
+    cancel_path_event_writer.send(CancelPathEvent::new(entity));
+    commands
+        .entity(entity)
+        .remove::<(
+            (Animal, Prey, Predator, Quarry, ThinkerBuilder, Personality),
+            (PhysicalNeeds, Satiety, LifeCycle, LifeStage),
+            (Speed, Stamina, Urgent, CharacterController, Interpolated),
+            (
+                Destination,
+                EntityPath,
+                Progress,
+                AgentSize,
+                AreaCosts,
+                AvoidanceAgent,
+            ),
+        )>()
+        .insert((
+            Carcass::new(CARCASS_CONFIG.meat),
+            Age::new(&time_controller),
+        ));
+}
+
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct EatCarcass {
+    until: f32,
+    per_second: f32,
+}
+
+impl EatCarcass {
+    pub fn new(until: f32, per_second: f32) -> Self {
+        Self { until, per_second }
+    }
+}
+
+fn eat_carcass_action_system(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    mut predators: Query<(&Quarry, &mut Satiety), With<Predator>>,
+    mut carcasses: Query<&mut Carcass>,
+    mut action_query: Query<(&Actor, &mut ActionState, &EatCarcass, &ActionSpan)>,
+    mut despawn_request_writer: EventWriter<DespawnRequest>,
+) {
+    for (Actor(actor), mut state, eat, span) in &mut action_query {
+        let _guard = span.span().enter();
+
+        match *state {
+            ActionState::Requested => {
+                debug!("Time to feed!");
+                *state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                let Ok((Quarry(target), mut satiety)) = predators.get_mut(*actor) else {
+                    *state = ActionState::Failure;
+                    continue;
+                };
+                let target = *target;
+                let Ok(mut carcass) = carcasses.get_mut(target) else {
+                    commands.entity(*actor).remove::<Quarry>();
+                    *state = ActionState::Failure;
+                    continue;
+                };
+
+                trace!("Feeding...");
+                let bite = (eat.per_second * time_controller.scaled_delta().as_secs_f32())
+                    .min(carcass.meat);
+                carcass.meat -= bite;
+                satiety.value = (satiety.value + bite).min(100.);
+
+                if carcass.meat <= 0. {
+                    debug!("Nothing left of the carcass");
+                    despawn_request_writer.send(DespawnRequest::new(target, DespawnCause::Eaten));
+                }
+                if satiety.value >= eat.until || carcass.meat <= 0. {
+                    debug!("Done feeding");
+                    commands.entity(*actor).remove::<Quarry>();
+                    *state = ActionState::Success;
+                }
+            }
+            ActionState::Cancelled => {
+                debug!("Feeding cancelled");
+                commands.entity(*actor).remove::<Quarry>();
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Scores how close the nearest predator within `radius` is, from 0 at the edge
+/// of `radius` to 1 right on top of the actor, mapped through `curve`.
+#[derive(Clone, Component, Debug, ScorerBuilder)]
+pub struct Scared {
+    radius: f32,
+    curve: ResponseCurve,
+}
+
+impl Scared {
+    pub fn new(radius: f32, curve: ResponseCurve) -> Self {
+        Self { radius, curve }
+    }
+}
+
+fn scared_scorer_system(
+    prey_query: Query<(&Transform, Option<&Personality>), With<Prey>>,
+    predator_index: Res<SpatialIndex<Predator>>,
+    mut query: Query<(&Actor, &mut Score, &Scared, &ScorerSpan)>,
+) {
+    for (Actor(actor), mut score, scared, span) in &mut query {
+        let Ok((transform, personality)) = prey_query.get(*actor) else {
+            score.set(0.);
+            continue;
+        };
+        let spotted = predator_index.nearest_within(transform.translation, scared.radius);
+        if let Some((_, predator)) = spotted {
+            span.span().in_scope(|| debug!("Predator spotted!"));
+            let proximity = 1. - transform.translation.distance(predator) / scared.radius;
+            let weight = personality.map_or(1., |p| p.fear_weight());
+            score.set((scared.curve.evaluate(proximity) * weight).min(1.));
+        } else {
+            score.set(0.);
+        }
+    }
+}
+
+#[derive(Clone, Component, Debug, ActionBuilder)]
+pub struct Flee {
+    safe_distance: f32,
+    flee_distance: f32,
+}
+
+impl Flee {
+    pub fn new(safe_distance: f32, flee_distance: f32) -> Self {
+        Self {
+            safe_distance,
+            flee_distance,
+        }
+    }
+}
+
+fn flee_action_system(
+    mut commands: Commands,
+    prey_query: Query<(&Transform, Has<EntityPath>, Option<&Personality>), With<Prey>>,
+    prey_index: Res<SpatialIndex<Prey>>,
+    predator_index: Res<SpatialIndex<Predator>>,
+    mut action_query: Query<(&Actor, &mut ActionState, &Flee, &ActionSpan)>,
+    mut find_path_event_writer: EventWriter<FindPathEvent>,
+    mut cancel_path_event_writer: EventWriter<CancelPathEvent>,
+) {
+    let mut rng = rand::rng();
+
+    for (Actor(actor), mut state, flee, span) in &mut action_query {
+        let _guard = span.span().enter();
+
+        match *state {
+            ActionState::Requested => {
+                debug!("Run!");
+                commands
+                    .entity(*actor)
+                    .remove::<(EntityPath, Destination)>()
+                    .insert(Urgent);
+                *state = ActionState::Executing;
+            }
+            ActionState::Executing => {
+                let Ok((transform, has_path, personality)) = prey_query.get(*actor) else {
+                    *state = ActionState::Failure;
+                    continue;
+                };
+                let Some((_, predator)) =
+                    predator_index.nearest_within(transform.translation, flee.safe_distance)
+                else {
+                    debug!("Got away");
+                    commands.entity(*actor).remove::<Urgent>();
+                    *state = ActionState::Success;
+                    continue;
+                };
+
+                if !has_path {
+                    let mut away = (transform.translation - predator)
+                        .with_y(0.)
+                        .normalize_or(Vec3::X);
+
+                    // Sociable animals bend their escape towards the rest of the herd.
+                    let herd = prey_index
+                        .nearest_k(transform.translation, 2)
+                        .into_iter()
+                        .find(|(other, _)| other != actor)
+                        .map(|(_, translation)| translation);
+                    if let (Some(herd), Some(personality)) = (herd, personality) {
+                        let to_herd = (herd - transform.translation)
+                            .with_y(0.)
+                            .normalize_or_zero();
+                        away = (away + to_herd * personality.sociability).normalize_or(away);
+                    }
+
+                    // Jitter the heading so a blocked escape route doesn't get retried forever.
+                    let away =
+                        Quat::from_rotation_y(rng.random_range(-FRAC_PI_4..FRAC_PI_4)) * away;
+                    find_path_event_writer.send(FindPathEvent::new(
+                        transform.translation,
+                        transform.translation + away * flee.flee_distance,
+                        *actor,
+                    ));
+                }
+            }
+            ActionState::Cancelled => {
+                commands.entity(*actor).remove::<Urgent>();
+                cancel_path_event_writer.send(CancelPathEvent::new(*actor));
+                *state = ActionState::Failure;
+            }
+            _ => {}
+        }
+    }
+}
+
+struct CarcassConfig {
+    meat: f32,
+    decay_check_sim_seconds: f32,
+    lifespan_days: f32,
+    /// Returned to the soil for each bit of meat left when it rots.
+    nutrients_per_meat: f32,
+}
@@ -6,6 +6,14 @@ pub enum StartupSet {
     StartupRoundB,
 }
 
+/// `PostUpdate` work that reads where things are in the simulation runs before
+/// they're moved to where they're drawn.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub enum PostUpdateSet {
+    Simulated,
+    Interpolated,
+}
+
 pub struct SchedulePlugin;
 
 impl Plugin for SchedulePlugin {
@@ -13,6 +21,14 @@ impl Plugin for SchedulePlugin {
         app.configure_sets(
             Startup,
             (StartupSet::StartupRoundA, StartupSet::StartupRoundB).chain(),
+        )
+        .configure_sets(
+            PostUpdate,
+            (
+                PostUpdateSet::Simulated,
+                PostUpdateSet::Interpolated.before(TransformSystem::TransformPropagate),
+            )
+                .chain(),
         );
     }
 }
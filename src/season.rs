@@ -0,0 +1,235 @@
+use std::{f32::consts::TAU, ops::Range};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::time_control::TimeController;
+
+const SEASON_CONFIG: SeasonConfig = SeasonConfig {
+    year_days: 365.,
+    start_day: 120.,
+    spring_start_day: 60.,
+    flowering_days: 60.0..120.0,
+    fruiting_days: 120.0..300.0,
+    peak_fruiting_rate: 2.5,
+    mean_temperature: 10.,
+    temperature_swing: 12.,
+    coldest_day: 15.,
+    decay_q10: 2.,
+    wind_check_sim_seconds: 60. * 60.,
+    wind_gustiness: 0.2,
+    wind_settling: 0.2,
+};
+
+pub struct SeasonPlugin;
+
+impl Plugin for SeasonPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Calendar>()
+            .register_type::<Wind>()
+            .insert_resource(Calendar {
+                day_of_year: SEASON_CONFIG.start_day,
+            })
+            .init_resource::<Wind>()
+            .insert_resource(WindTimer {
+                timer: Timer::from_seconds(
+                    SEASON_CONFIG.wind_check_sim_seconds,
+                    TimerMode::Repeating,
+                ),
+            })
+            .add_systems(FixedUpdate, (advance_calendar, change_wind).chain());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    /// How windy it usually is, from 0 to 1.
+    pub fn mean_wind(&self) -> f32 {
+        match self {
+            Season::Spring => 0.3,
+            Season::Summer => 0.2,
+            Season::Autumn => 0.5,
+            Season::Winter => 0.4,
+        }
+    }
+}
+
+/// What trees are up to at this time of year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreePhase {
+    Flowering,
+    Fruiting,
+    /// Nothing on the branches, through autumn and winter.
+    Bare,
+}
+
+/// Where we are in the year, which drives the seasons and the weather.
+#[derive(Resource, Clone, Copy, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct Calendar {
+    /// From 0 up to the number of days in a year.
+    day_of_year: f32,
+}
+
+impl Calendar {
+    pub fn day_of_year(&self) -> f32 {
+        self.day_of_year
+    }
+
+    pub fn season(&self) -> Season {
+        let quarter = SEASON_CONFIG.year_days / 4.;
+        let since_spring =
+            (self.day_of_year - SEASON_CONFIG.spring_start_day).rem_euclid(SEASON_CONFIG.year_days);
+        match (since_spring / quarter) as u32 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn tree_phase(&self) -> TreePhase {
+        if SEASON_CONFIG.flowering_days.contains(&self.day_of_year) {
+            TreePhase::Flowering
+        } else if SEASON_CONFIG.fruiting_days.contains(&self.day_of_year) {
+            TreePhase::Fruiting
+        } else {
+            TreePhase::Bare
+        }
+    }
+
+    /// Multiplier on how often trees fruit. Zero outside the fruiting window, and
+    /// peaking halfway through it.
+    pub fn fruiting_rate(&self) -> f64 {
+        if self.tree_phase() != TreePhase::Fruiting {
+            return 0.;
+        }
+        let window = &SEASON_CONFIG.fruiting_days;
+        let t = (self.day_of_year - window.start) / (window.end - window.start);
+        (SEASON_CONFIG.peak_fruiting_rate * (0.5 * TAU * t).sin()) as f64
+    }
+
+    /// Air temperature in degrees Celsius, coldest in midwinter.
+    pub fn temperature(&self) -> f32 {
+        let angle = TAU * (self.day_of_year - SEASON_CONFIG.coldest_day) / SEASON_CONFIG.year_days;
+        SEASON_CONFIG.mean_temperature - SEASON_CONFIG.temperature_swing * angle.cos()
+    }
+
+    /// Multiplier on how fast things rot, 1 at the mean temperature. Doubles for
+    /// every 10 degrees warmer, and halves for every 10 colder.
+    pub fn decay_rate(&self) -> f32 {
+        let warmer = self.temperature() - SEASON_CONFIG.mean_temperature;
+        SEASON_CONFIG.decay_q10.powf(warmer / 10.)
+    }
+}
+
+/// How windy it is right now, from 0 (still) to 1 (a gale).
+#[derive(Resource, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Wind {
+    strength: f32,
+}
+
+impl Wind {
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WindTimer {
+    timer: Timer,
+}
+
+fn advance_calendar(time_controller: Res<TimeController>, mut calendar: ResMut<Calendar>) {
+    let days = (time_controller.simulated_elapsed_secs() / (60. * 60. * 24.)) as f32;
+    calendar.day_of_year = (SEASON_CONFIG.start_day + days).rem_euclid(SEASON_CONFIG.year_days);
+}
+
+/// Gusts come and go, but the wind keeps drifting back to what's usual for the season.
+fn change_wind(
+    time_controller: Res<TimeController>,
+    calendar: Res<Calendar>,
+    mut wind_timer: ResMut<WindTimer>,
+    mut wind: ResMut<Wind>,
+) {
+    wind_timer.timer.tick(time_controller.scaled_delta());
+    if !wind_timer.timer.just_finished() {
+        return;
+    }
+
+    let mut rng = rand::rng();
+    let gust = rng.random_range(-SEASON_CONFIG.wind_gustiness..=SEASON_CONFIG.wind_gustiness);
+    let settle = (calendar.season().mean_wind() - wind.strength) * SEASON_CONFIG.wind_settling;
+    wind.strength = (wind.strength + settle + gust).clamp(0., 1.);
+}
+
+struct SeasonConfig {
+    year_days: f32,
+    /// Day of the year the world starts on.
+    start_day: f32,
+    spring_start_day: f32,
+    flowering_days: Range<f32>,
+    fruiting_days: Range<f32>,
+    /// `fruiting_rate` at the height of the fruiting window.
+    peak_fruiting_rate: f32,
+    mean_temperature: f32,
+    /// How far above and below the mean the temperature goes over the year.
+    temperature_swing: f32,
+    coldest_day: f32,
+    /// How many times faster things rot for every 10 degrees warmer.
+    decay_q10: f32,
+    wind_check_sim_seconds: f32,
+    /// Most the wind changes by from one check to the next.
+    wind_gustiness: f32,
+    /// Fraction of the way back to the season's usual wind it goes each check.
+    wind_settling: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on(day_of_year: f32) -> Calendar {
+        Calendar { day_of_year }
+    }
+
+    #[test]
+    fn trees_flower_then_fruit_then_go_bare() {
+        assert_eq!(on(20.).tree_phase(), TreePhase::Bare);
+        assert_eq!(on(90.).tree_phase(), TreePhase::Flowering);
+        assert_eq!(on(200.).tree_phase(), TreePhase::Fruiting);
+        assert_eq!(on(330.).tree_phase(), TreePhase::Bare);
+
+        assert_eq!(on(20.).fruiting_rate(), 0.);
+        assert_eq!(on(90.).fruiting_rate(), 0.);
+        assert!(on(210.).fruiting_rate() > on(130.).fruiting_rate());
+        assert!((on(210.).fruiting_rate() - 2.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn seasons_follow_the_spring_start() {
+        assert_eq!(on(60.).season(), Season::Spring);
+        assert_eq!(on(200.).season(), Season::Summer);
+        assert_eq!(on(300.).season(), Season::Autumn);
+        assert_eq!(on(10.).season(), Season::Winter);
+    }
+
+    #[test]
+    fn things_rot_faster_in_summer() {
+        let winter = on(SEASON_CONFIG.coldest_day);
+        let summer = on(SEASON_CONFIG.coldest_day + 0.5 * SEASON_CONFIG.year_days);
+
+        assert!((winter.temperature() + 2.).abs() < 1e-3);
+        assert!((summer.temperature() - 22.).abs() < 1e-3);
+        assert!(winter.decay_rate() < 1.);
+        assert!(summer.decay_rate() > 2.);
+    }
+}
@@ -0,0 +1,431 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use rand::Rng;
+
+use crate::{
+    age::Age,
+    animal::Animal,
+    biome::BiomeMap,
+    bounds::WorldBounds,
+    despawn::{DespawnCause, DespawnRequest},
+    needs::Satiety,
+    terrain::Terrain,
+    time_control::TimeController,
+};
+
+const SOIL_CONFIG: SoilConfig = SoilConfig {
+    cell_size: 2.,
+    max_nutrients: 1.,
+    ideal_moisture: 0.5,
+    weathering_check_sim_seconds: 60. * 60. * 24.,
+    weathering_per_day: 0.02,
+    overlay_refresh_seconds: 1.,
+    overlay_height: 0.05,
+};
+
+const DUNG_CONFIG: DungConfig = DungConfig {
+    drop_check_sim_seconds: 60. * 60. * 6.,
+    drop_probability: 0.5,
+    min_satiety: 50.,
+    nutrients: 0.05,
+    decay_check_sim_seconds: 60. * 60.,
+    lifespan_days: 2.,
+    radius: 0.08,
+};
+
+pub struct SoilPlugin;
+
+impl Plugin for SoilPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SoilOverlay>()
+            // Made from the biomes, so `GroundPlugin` has to be added first.
+            .init_resource::<SoilGrid>()
+            .init_resource::<SoilOverlay>()
+            .init_resource::<DungAssets>()
+            .insert_resource(WeatheringTimer {
+                timer: Timer::from_seconds(
+                    SOIL_CONFIG.weathering_check_sim_seconds,
+                    TimerMode::Repeating,
+                ),
+            })
+            .insert_resource(DungTimer {
+                timer: Timer::from_seconds(
+                    DUNG_CONFIG.drop_check_sim_seconds,
+                    TimerMode::Repeating,
+                ),
+            })
+            .insert_resource(DungDecayTimer {
+                timer: Timer::from_seconds(
+                    DUNG_CONFIG.decay_check_sim_seconds,
+                    TimerMode::Repeating,
+                ),
+            })
+            .add_systems(Startup, spawn_soil_overlay)
+            .add_systems(FixedUpdate, (weather_soil, drop_dung, decay_dung))
+            .add_systems(
+                Update,
+                (
+                    cycle_soil_overlay
+                        .run_if(|keys: Res<ButtonInput<KeyCode>>| keys.just_pressed(KeyCode::KeyN)),
+                    update_soil_overlay.run_if(resource_changed::<SoilOverlay>.or(on_timer(
+                        Duration::from_secs_f32(SOIL_CONFIG.overlay_refresh_seconds),
+                    ))),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WeatheringTimer {
+    timer: Timer,
+}
+
+#[derive(Resource, Debug)]
+pub struct DungTimer {
+    timer: Timer,
+}
+
+#[derive(Resource, Debug)]
+pub struct DungDecayTimer {
+    timer: Timer,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Soil {
+    /// From 0 to `SOIL_CONFIG.max_nutrients`.
+    pub nutrients: f32,
+    /// From 0 (dry) to 1 (waterlogged).
+    pub moisture: f32,
+}
+
+/// Nutrients and moisture in every cell of a grid over the whole ground. Plants
+/// draw nutrients out and whatever rots returns them.
+#[derive(Resource, Clone, Debug)]
+pub struct SoilGrid {
+    half_extent: f32,
+    cell_size: f32,
+    cells_per_side: i32,
+    cells: Vec<Soil>,
+}
+
+impl FromWorld for SoilGrid {
+    fn from_world(world: &mut World) -> Self {
+        let half_extent = 0.5 * world.resource::<WorldBounds>().ground_size();
+        let biome_map = world.resource::<BiomeMap>();
+        SoilGrid::new(half_extent, SOIL_CONFIG.cell_size, |position| {
+            let biome = biome_map.biome_at(position).config();
+            Soil {
+                nutrients: biome.soil_nutrients,
+                moisture: biome.soil_moisture,
+            }
+        })
+    }
+}
+
+impl SoilGrid {
+    pub fn new(half_extent: f32, cell_size: f32, soil_at: impl Fn(Vec3) -> Soil) -> Self {
+        let cells_per_side = (2. * half_extent / cell_size).ceil() as i32;
+        let mut grid = Self {
+            half_extent,
+            cell_size,
+            cells_per_side,
+            cells: Vec::with_capacity((cells_per_side * cells_per_side) as usize),
+        };
+        for z in 0..cells_per_side {
+            for x in 0..cells_per_side {
+                let soil = soil_at(grid.center_of(IVec2::new(x, z)));
+                grid.cells.push(soil);
+            }
+        }
+        grid
+    }
+
+    fn center_of(&self, cell: IVec2) -> Vec3 {
+        Vec3::new(
+            (cell.x as f32 + 0.5) * self.cell_size - self.half_extent,
+            0.,
+            (cell.y as f32 + 0.5) * self.cell_size - self.half_extent,
+        )
+    }
+
+    /// Past the edge of the grid this is the nearest edge cell.
+    fn index_of(&self, position: Vec3) -> usize {
+        let last = self.cells_per_side - 1;
+        let x = (((position.x + self.half_extent) / self.cell_size).floor() as i32).clamp(0, last);
+        let z = (((position.z + self.half_extent) / self.cell_size).floor() as i32).clamp(0, last);
+        (z * self.cells_per_side + x) as usize
+    }
+
+    pub fn soil_at(&self, position: Vec3) -> Soil {
+        self.cells[self.index_of(position)]
+    }
+
+    /// Takes up to `nutrients` out of the soil under `position` for something growing
+    /// there. Returns how well that went, from 0 to 1: short of either nutrients or
+    /// water brings it down.
+    pub fn draw(&mut self, position: Vec3, nutrients: f32) -> f32 {
+        let index = self.index_of(position);
+        let soil = &mut self.cells[index];
+        let watered = (soil.moisture / SOIL_CONFIG.ideal_moisture).min(1.);
+        if nutrients <= 0. {
+            return watered;
+        }
+        let taken = nutrients.min(soil.nutrients);
+        soil.nutrients -= taken;
+        taken / nutrients * watered
+    }
+
+    /// Returns nutrients to the soil under `position`, e.g. from something rotting.
+    pub fn deposit(&mut self, position: Vec3, nutrients: f32) {
+        let index = self.index_of(position);
+        let soil = &mut self.cells[index];
+        soil.nutrients = (soil.nutrients + nutrients).min(SOIL_CONFIG.max_nutrients);
+    }
+}
+
+/// What the soil overlay shows, if anything. Cycle through with N.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum SoilOverlay {
+    #[default]
+    Off,
+    Nutrients,
+    Moisture,
+}
+
+impl SoilOverlay {
+    /// The shown value, from 0 to 1.
+    fn value(&self, soil: Soil) -> f32 {
+        match self {
+            SoilOverlay::Off => 0.,
+            SoilOverlay::Nutrients => soil.nutrients / SOIL_CONFIG.max_nutrients,
+            SoilOverlay::Moisture => soil.moisture,
+        }
+    }
+}
+
+/// The heatmap drawn over the ground.
+#[derive(Component, Debug)]
+struct SoilOverlayMesh;
+
+/// Droppings, which rot back into the soil.
+#[derive(Component, Debug)]
+pub struct Dung {
+    nutrients: f32,
+}
+
+#[derive(Resource, Debug)]
+struct DungAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for DungAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Sphere::new(DUNG_CONFIG.radius));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::srgb(0.3, 0.2, 0.1));
+        Self { mesh, material }
+    }
+}
+
+fn weather_soil(
+    time_controller: Res<TimeController>,
+    mut weathering_timer: ResMut<WeatheringTimer>,
+    mut soil_grid: ResMut<SoilGrid>,
+) {
+    weathering_timer.timer.tick(time_controller.scaled_delta());
+    if !weathering_timer.timer.just_finished() {
+        return;
+    }
+
+    // Rock slowly breaking down keeps even bare ground from running out for good.
+    let days = SOIL_CONFIG.weathering_check_sim_seconds / (60. * 60. * 24.);
+    for soil in soil_grid.cells.iter_mut() {
+        soil.nutrients =
+            (soil.nutrients + SOIL_CONFIG.weathering_per_day * days).min(SOIL_CONFIG.max_nutrients);
+    }
+}
+
+fn drop_dung(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    dung_assets: Res<DungAssets>,
+    mut dung_timer: ResMut<DungTimer>,
+    query: Query<(&Transform, &Satiety), With<Animal>>,
+) {
+    dung_timer.timer.tick(time_controller.scaled_delta());
+    if !dung_timer.timer.just_finished() {
+        return;
+    }
+
+    let mut rng = rand::rng();
+    for (transform, satiety) in query.iter() {
+        if satiety.value < DUNG_CONFIG.min_satiety || !rng.random_bool(DUNG_CONFIG.drop_probability)
+        {
+            continue;
+        }
+        commands.spawn((
+            Name::new("Dung"),
+            Mesh3d(dung_assets.mesh.clone()),
+            MeshMaterial3d(dung_assets.material.clone()),
+            Transform::from_translation(transform.translation + Vec3::Y * DUNG_CONFIG.radius),
+            Dung {
+                nutrients: DUNG_CONFIG.nutrients,
+            },
+            Age::new(&time_controller),
+        ));
+    }
+}
+
+fn decay_dung(
+    mut commands: Commands,
+    query: Query<(Entity, &Age, &Transform, &Dung)>,
+    time_controller: Res<TimeController>,
+    mut soil_grid: ResMut<SoilGrid>,
+    mut decay_timer: ResMut<DungDecayTimer>,
+    mut despawn_request_writer: EventWriter<DespawnRequest>,
+) {
+    decay_timer.timer.tick(time_controller.scaled_delta());
+    if !decay_timer.timer.just_finished() {
+        return;
+    }
+
+    for (entity, age, transform, dung) in query.iter() {
+        if age.age_days(&time_controller) as f32 > DUNG_CONFIG.lifespan_days {
+            soil_grid.deposit(transform.translation, dung.nutrients);
+            // See `DespawnRequest`.
+            commands.entity(entity).remove::<Dung>();
+            despawn_request_writer.send(DespawnRequest::new(entity, DespawnCause::Decayed));
+        }
+    }
+}
+
+fn spawn_soil_overlay(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.spawn((
+        Name::new("Soil overlay"),
+        SoilOverlayMesh,
+        // Filled in by `update_soil_overlay`.
+        Mesh3d(meshes.add(Mesh::from(Plane3d::default()))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        })),
+        Transform::from_xyz(0., SOIL_CONFIG.overlay_height, 0.),
+        Visibility::Hidden,
+        PickingBehavior::IGNORE,
+    ));
+}
+
+fn cycle_soil_overlay(mut overlay: ResMut<SoilOverlay>) {
+    *overlay = match *overlay {
+        SoilOverlay::Off => SoilOverlay::Nutrients,
+        SoilOverlay::Nutrients => SoilOverlay::Moisture,
+        SoilOverlay::Moisture => SoilOverlay::Off,
+    };
+    info!("Soil overlay: {:?}", *overlay);
+}
+
+fn update_soil_overlay(
+    overlay: Res<SoilOverlay>,
+    soil_grid: Res<SoilGrid>,
+    terrain: Res<Terrain>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Mesh3d, &mut Visibility), With<SoilOverlayMesh>>,
+) {
+    for (mesh, mut visibility) in query.iter_mut() {
+        if *overlay == SoilOverlay::Off {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            // Blue for none, through green, to red for plenty.
+            *mesh = terrain.mesh(|position| {
+                let value = overlay.value(soil_grid.soil_at(position)).clamp(0., 1.);
+                Color::hsla(240. * (1. - value), 0.9, 0.5, 0.6)
+            });
+        }
+    }
+}
+
+struct SoilConfig {
+    cell_size: f32,
+    max_nutrients: f32,
+    /// Moisture at and above which growth isn't held back by water.
+    ideal_moisture: f32,
+    weathering_check_sim_seconds: f32,
+    /// Nutrients every cell gains a day, however bare.
+    weathering_per_day: f32,
+    overlay_refresh_seconds: f32,
+    /// How far above the ground the overlay floats.
+    overlay_height: f32,
+}
+
+struct DungConfig {
+    drop_check_sim_seconds: f32,
+    drop_probability: f64,
+    /// Animals hungrier than this don't drop any.
+    min_satiety: f32,
+    nutrients: f32,
+    decay_check_sim_seconds: f32,
+    lifespan_days: f32,
+    radius: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(soil: Soil) -> SoilGrid {
+        SoilGrid::new(5., 1., |_| soil)
+    }
+
+    #[test]
+    fn draw_takes_what_there_is_and_reports_shortfalls() {
+        let mut soil = grid(Soil {
+            nutrients: 0.3,
+            moisture: 1.,
+        });
+        let at = Vec3::new(1.5, 0., -2.5);
+
+        assert_eq!(soil.draw(at, 0.2), 1.);
+        assert!((soil.draw(at, 0.2) - 0.5).abs() < 1e-5);
+        assert_eq!(soil.soil_at(at).nutrients, 0.);
+        // Neighbouring cells are untouched.
+        assert_eq!(soil.soil_at(at + Vec3::X).nutrients, 0.3);
+    }
+
+    #[test]
+    fn dry_soil_holds_growth_back() {
+        let mut soil = grid(Soil {
+            nutrients: 1.,
+            moisture: 0.5 * SOIL_CONFIG.ideal_moisture,
+        });
+
+        assert!((soil.draw(Vec3::ZERO, 0.1) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn deposits_are_capped() {
+        let mut soil = grid(Soil::default());
+        soil.deposit(Vec3::ZERO, 0.4);
+        soil.deposit(Vec3::ZERO, 2.);
+
+        assert_eq!(
+            soil.soil_at(Vec3::ZERO).nutrients,
+            SOIL_CONFIG.max_nutrients
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{despawn::Despawning, schedule::PostUpdateSet};
+
+/// Keeps a `SpatialIndex<T>` in sync with the `Transform`s of every entity with a `T`.
+pub struct SpatialIndexPlugin<T: Component> {
+    cell_size: f32,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Component> SpatialIndexPlugin<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> Plugin for SpatialIndexPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpatialIndex::<T>::new(self.cell_size))
+            // Transforms only move in FixedUpdate, which runs before PostUpdate in
+            // the same frame, so readers in PreUpdate and FixedUpdate see fresh data.
+            // Interpolation hasn't moved them yet, so these are the simulated positions.
+            .add_systems(
+                PostUpdate,
+                sync_spatial_index::<T>.in_set(PostUpdateSet::Simulated),
+            )
+            // Despawns happen in Last, after the sync, so drop them straight away
+            // rather than leaving them in until next frame.
+            .add_observer(remove_despawning::<T>);
+    }
+}
+
+/// A uniform grid over the XZ plane, bucketing entities with a `T` by position.
+#[derive(Resource, Debug)]
+pub struct SpatialIndex<T: Component> {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec3)>>,
+    entries: HashMap<Entity, IVec2>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Component> SpatialIndex<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            entries: HashMap::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec2 {
+        IVec2::new(
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Adds the entity, or moves it if it's already in the index.
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        let cell = self.cell_of(position);
+        if let Some(old_cell) = self.entries.insert(entity, cell) {
+            if let Some(bucket) = self.cells.get_mut(&old_cell) {
+                bucket.retain(|(e, _)| *e != entity);
+                if bucket.is_empty() {
+                    self.cells.remove(&old_cell);
+                }
+            }
+        }
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(cell) = self.entries.remove(&entity) else {
+            return;
+        };
+        if let Some(bucket) = self.cells.get_mut(&cell) {
+            bucket.retain(|(e, _)| *e != entity);
+            if bucket.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Every entity within `radius` of `center`, in no particular order.
+    pub fn within_radius(&self, center: Vec3, radius: f32) -> Vec<(Entity, Vec3)> {
+        let min = self.cell_of(center - Vec3::splat(radius));
+        let max = self.cell_of(center + Vec3::splat(radius));
+        let radius_sqrd = radius * radius;
+
+        let mut found = Vec::new();
+        for x in min.x..=max.x {
+            for z in min.y..=max.y {
+                let Some(bucket) = self.cells.get(&IVec2::new(x, z)) else {
+                    continue;
+                };
+                found.extend(
+                    bucket
+                        .iter()
+                        .filter(|(_, p)| planar_dist_sqrd(*p, center) <= radius_sqrd),
+                );
+            }
+        }
+        found
+    }
+
+    /// Up to `k` entities closest to `center`, nearest first.
+    pub fn nearest_k(&self, center: Vec3, k: usize) -> Vec<(Entity, Vec3)> {
+        let mut found: Vec<(Entity, Vec3, f32)> = Vec::new();
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let center_cell = self.cell_of(center);
+        let mut seen = 0;
+        let mut ring = 0;
+        while seen < self.entries.len() {
+            for cell in ring_cells(center_cell, ring) {
+                let Some(bucket) = self.cells.get(&cell) else {
+                    continue;
+                };
+                seen += bucket.len();
+                found.extend(
+                    bucket
+                        .iter()
+                        .map(|(e, p)| (*e, *p, planar_dist_sqrd(*p, center))),
+                );
+            }
+
+            // Anything in the next ring out is at least this far away.
+            let unexplored = ring as f32 * self.cell_size;
+            if found.len() >= k {
+                found.sort_by(|a, b| a.2.total_cmp(&b.2));
+                if found[k - 1].2 <= unexplored * unexplored {
+                    break;
+                }
+            }
+            ring += 1;
+        }
+
+        found.sort_by(|a, b| a.2.total_cmp(&b.2));
+        found.truncate(k);
+        found.into_iter().map(|(e, p, _)| (e, p)).collect()
+    }
+
+    pub fn nearest(&self, center: Vec3) -> Option<(Entity, Vec3)> {
+        self.nearest_k(center, 1).into_iter().next()
+    }
+
+    pub fn nearest_within(&self, center: Vec3, radius: f32) -> Option<(Entity, Vec3)> {
+        self.nearest(center)
+            .filter(|(_, p)| planar_dist_sqrd(*p, center) <= radius * radius)
+    }
+}
+
+/// Distance on the ground plane; the grid ignores height.
+fn planar_dist_sqrd(a: Vec3, b: Vec3) -> f32 {
+    a.xz().distance_squared(b.xz())
+}
+
+/// The cells exactly `ring` steps away from `center` (Chebyshev distance).
+fn ring_cells(center: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+    (-ring..=ring).flat_map(move |x| {
+        (-ring..=ring)
+            .filter(move |z| x.abs() == ring || z.abs() == ring)
+            .map(move |z| center + IVec2::new(x, z))
+    })
+}
+
+fn sync_spatial_index<T: Component>(
+    mut index: ResMut<SpatialIndex<T>>,
+    moved: Query<(Entity, &Transform), (With<T>, Or<(Changed<Transform>, Added<T>)>)>,
+    mut removed: RemovedComponents<T>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    for (entity, transform) in moved.iter() {
+        index.insert(entity, transform.translation);
+    }
+}
+
+fn remove_despawning<T: Component>(
+    trigger: Trigger<Despawning>,
+    mut index: ResMut<SpatialIndex<T>>,
+) {
+    index.remove(trigger.entity());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn index_with(points: &[Vec3]) -> SpatialIndex<Marker> {
+        let mut index = SpatialIndex::new(2.0);
+        for (i, point) in points.iter().enumerate() {
+            index.insert(Entity::from_raw(i as u32), *point);
+        }
+        index
+    }
+
+    #[test]
+    fn nearest_k_is_sorted_and_exact() {
+        let index = index_with(&[
+            Vec3::new(10., 0., 10.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(-3., 0., 0.5),
+            Vec3::new(0.5, 0., -7.),
+        ]);
+        let nearest: Vec<Entity> = index
+            .nearest_k(Vec3::ZERO, 3)
+            .into_iter()
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(
+            nearest,
+            vec![
+                Entity::from_raw(1),
+                Entity::from_raw(2),
+                Entity::from_raw(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn nearest_k_with_fewer_entries() {
+        let index = index_with(&[Vec3::new(40., 0., -40.)]);
+        assert_eq!(index.nearest_k(Vec3::ZERO, 5).len(), 1);
+        assert!(index_with(&[]).nearest(Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn within_radius_filters_by_distance() {
+        let index = index_with(&[
+            Vec3::new(1., 0., 1.),
+            Vec3::new(2.9, 0., 0.),
+            Vec3::new(3.1, 0., 0.),
+        ]);
+        let mut found: Vec<Entity> = index
+            .within_radius(Vec3::ZERO, 3.)
+            .into_iter()
+            .map(|(e, _)| e)
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![Entity::from_raw(0), Entity::from_raw(1)]);
+    }
+
+    #[test]
+    fn moving_and_removing() {
+        let mut index = index_with(&[Vec3::ZERO, Vec3::new(5., 0., 5.)]);
+        index.insert(Entity::from_raw(0), Vec3::new(20., 0., 20.));
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.nearest(Vec3::ZERO).map(|(e, _)| e),
+            Some(Entity::from_raw(1))
+        );
+
+        index.remove(Entity::from_raw(1));
+        assert_eq!(
+            index.nearest(Vec3::ZERO).map(|(e, _)| e),
+            Some(Entity::from_raw(0))
+        );
+        assert!(index.nearest_within(Vec3::ZERO, 10.).is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::prelude::*;
+use big_brain::{
+    prelude::{FirstToScore, Steps},
+    thinker::{Thinker, ThinkerBuilder},
+};
+
+use crate::{
+    animal::AnimalType,
+    areas::AreaCosts,
+    biome::BiomeWeights,
+    life_cycle::{LifeCycleConfig, MortalityCurve, PerStage, StageModifiers},
+    movement::{Speed, Stamina, Steering},
+    navigation::AgentSize,
+    needs::{Browse, CanBrowse, Eat, Hungry, MoveToNearest},
+    predation::{Chase, EatCarcass, Flee, Kill, Scared, Stalk},
+    utility::{HighestWithInertia, ResponseCurve},
+    vegetation::Fruit,
+};
+
+const DAYS_PER_YEAR: f32 = 365.;
+
+static DEER: SpeciesConfig = SpeciesConfig {
+    initial_count: 10,
+    variants: &[
+        VariantConfig {
+            name: "deer",
+            collider_size: Vec3::new(0.25, 1.0, 1.1),
+            collider_offset: Vec3::new(0.0, 2.25, 0.5),
+            browse_height: 5.6,
+        },
+        VariantConfig {
+            name: "stag",
+            collider_size: Vec3::new(0.35, 1.3, 1.1),
+            collider_offset: Vec3::new(0.0, 2.30, 0.5),
+            // Stags stretch up past what does can reach.
+            browse_height: 8.0,
+        },
+    ],
+    speed: Speed::new(1.125, 2.0, 3.5),
+    stamina: Stamina::new(100.0, 2.5, 0.5),
+    // Quick off the mark, but need more room to turn than wolves do.
+    steering: Steering::new(1.5, 4.0, 3.0, 2.0, 1.0),
+    diet: Diet::Herbivore,
+    // Out in the open is where wolves spot them.
+    area_costs: AreaCosts {
+        meadow: 1.5,
+        forest: 1.0,
+        water: f32::INFINITY,
+    },
+    // Grazers, keeping near cover.
+    biome_preference: BiomeWeights {
+        meadow: 1.0,
+        woodland: 0.7,
+        wetland: 0.3,
+        rocky: 0.1,
+    },
+    perception_radius: 10.0,
+    behavior: BehaviorConfig {
+        picker: PickerConfig::HighestWithInertia {
+            threshold: 0.5,
+            inertia: 0.1,
+        },
+        hunger: ResponseCurve::Logistic {
+            steepness: 12.,
+            midpoint: 0.75,
+        },
+        fear: ResponseCurve::Piecewise(&[(0., 0.), (0.3, 0.7), (0.6, 1.), (1., 1.)]),
+        eat_until: 90.,
+        eat_per_second: 0.5,
+        flee_distance: 10.,
+        pounce_distance: 0.,
+        kill_distance: 0.,
+        give_up_distance: 0.,
+    },
+    scale: 0.25,
+    life_cycle: LifeCycleConfig {
+        juvenile_days: 30.,
+        adult_days: DAYS_PER_YEAR,
+        senescent_days: 8. * DAYS_PER_YEAR,
+        mortality: MortalityCurve {
+            onset_days: 8. * DAYS_PER_YEAR,
+            initial_daily_hazard: 0.0005,
+            doubling_days: DAYS_PER_YEAR,
+        },
+    },
+    stage_modifiers: PerStage {
+        newborn: StageModifiers {
+            scale: 0.4,
+            speed: 0.6,
+            need_rate: 0.5,
+        },
+        juvenile: StageModifiers {
+            scale: 0.7,
+            speed: 0.9,
+            need_rate: 0.8,
+        },
+        adult: StageModifiers {
+            scale: 1.,
+            speed: 1.,
+            need_rate: 1.,
+        },
+        senescent: StageModifiers {
+            scale: 1.,
+            speed: 0.8,
+            need_rate: 0.9,
+        },
+    },
+};
+
+static WOLF: SpeciesConfig = SpeciesConfig {
+    initial_count: 2,
+    variants: &[VariantConfig {
+        name: "wolf",
+        collider_size: Vec3::new(0.3, 0.9, 1.2),
+        collider_offset: Vec3::new(0.0, 1.5, 0.3),
+        browse_height: 0.,
+    }],
+    speed: Speed::new(1.25, 2.25, 3.25),
+    stamina: Stamina::new(100.0, 1.0, 0.5),
+    steering: Steering::new(1.0, 3.0, 4.0, 2.5, 1.0),
+    diet: Diet::Carnivore,
+    area_costs: AreaCosts {
+        meadow: 1.0,
+        forest: 1.2,
+        water: 4.0,
+    },
+    biome_preference: BiomeWeights {
+        meadow: 0.3,
+        woodland: 1.0,
+        wetland: 0.2,
+        rocky: 0.7,
+    },
+    perception_radius: 20.0,
+    behavior: BehaviorConfig {
+        picker: PickerConfig::FirstToScore { threshold: 0.8 },
+        hunger: ResponseCurve::IDENTITY,
+        fear: ResponseCurve::IDENTITY,
+        eat_until: 90.,
+        eat_per_second: 0.5,
+        flee_distance: 0.,
+        pounce_distance: 5.,
+        kill_distance: 1.5,
+        give_up_distance: 12.,
+    },
+    scale: 0.25,
+    life_cycle: LifeCycleConfig {
+        juvenile_days: 60.,
+        adult_days: 2. * DAYS_PER_YEAR,
+        senescent_days: 9. * DAYS_PER_YEAR,
+        mortality: MortalityCurve {
+            onset_days: 9. * DAYS_PER_YEAR,
+            initial_daily_hazard: 0.0005,
+            doubling_days: DAYS_PER_YEAR,
+        },
+    },
+    stage_modifiers: PerStage {
+        newborn: StageModifiers {
+            scale: 0.4,
+            speed: 0.5,
+            need_rate: 0.5,
+        },
+        juvenile: StageModifiers {
+            scale: 0.75,
+            speed: 0.9,
+            need_rate: 0.9,
+        },
+        adult: StageModifiers {
+            scale: 1.,
+            speed: 1.,
+            need_rate: 1.,
+        },
+        senescent: StageModifiers {
+            scale: 1.,
+            speed: 0.75,
+            need_rate: 0.9,
+        },
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diet {
+    Herbivore,
+    Carnivore,
+}
+
+pub struct SpeciesConfig {
+    pub initial_count: u32,
+    pub variants: &'static [VariantConfig],
+    pub speed: Speed,
+    pub stamina: Stamina,
+    pub steering: Steering,
+    pub diet: Diet,
+    pub area_costs: AreaCosts,
+    /// Where it's likely to be found when the world starts.
+    pub biome_preference: BiomeWeights,
+    /// How far away the animal notices other animals, in world units.
+    pub perception_radius: f32,
+    pub behavior: BehaviorConfig,
+    /// Scale of an adult, before life stage modifiers.
+    pub scale: f32,
+    pub life_cycle: LifeCycleConfig,
+    pub stage_modifiers: PerStage<StageModifiers>,
+}
+
+pub enum PickerConfig {
+    FirstToScore { threshold: f32 },
+    HighestWithInertia { threshold: f32, inertia: f32 },
+}
+
+/// How a species scores its needs and what it does about them. Prey-only and
+/// predator-only fields are ignored by the other diet.
+pub struct BehaviorConfig {
+    pub picker: PickerConfig,
+    /// Maps hunger (0 when full, 1 when starving) to a score.
+    pub hunger: ResponseCurve,
+    /// Maps predator proximity (0 at the edge of perception, 1 adjacent) to a score.
+    pub fear: ResponseCurve,
+    pub eat_until: f32,
+    pub eat_per_second: f32,
+    pub flee_distance: f32,
+    pub pounce_distance: f32,
+    pub kill_distance: f32,
+    pub give_up_distance: f32,
+}
+
+/// A model variant of a species, e.g. does and stags are both deer.
+pub struct VariantConfig {
+    /// Used both as the scene key and the animation key.
+    pub name: &'static str,
+    pub collider_size: Vec3,
+    pub collider_offset: Vec3,
+    /// How high it can reach to eat fruit off trees, before scaling.
+    pub browse_height: f32,
+}
+
+impl VariantConfig {
+    /// Radius of the circle around the collider's footprint, before scaling.
+    pub fn radius(&self) -> f32 {
+        0.5 * self.collider_size.xz().length()
+    }
+
+    /// Which navmesh it paths on once scaled by `scale`.
+    pub fn agent_size(&self, scale: f32) -> AgentSize {
+        AgentSize::for_radius(self.radius() * scale)
+    }
+}
+
+impl SpeciesConfig {
+    pub fn thinker(&self) -> ThinkerBuilder {
+        let behavior = &self.behavior;
+        let thinker = match behavior.picker {
+            PickerConfig::FirstToScore { threshold } => {
+                Thinker::build().picker(FirstToScore { threshold })
+            }
+            PickerConfig::HighestWithInertia { threshold, inertia } => {
+                Thinker::build().picker(HighestWithInertia::new(threshold, inertia))
+            }
+        };
+
+        match self.diet {
+            Diet::Herbivore => {
+                let move_and_eat = Steps::build()
+                    .label("MoveAndEat")
+                    .step(MoveToNearest::<Fruit>::new())
+                    .step(Eat::new(behavior.eat_until, behavior.eat_per_second));
+
+                thinker
+                    .label("AnimaThinker")
+                    .when(
+                        Scared::new(self.perception_radius, behavior.fear),
+                        Flee::new(self.perception_radius * 1.5, behavior.flee_distance),
+                    )
+                    // Fruit still on the tree beats hunting about on the ground.
+                    .when(
+                        CanBrowse::new(behavior.hunger),
+                        Browse::new(behavior.eat_until, behavior.eat_per_second),
+                    )
+                    .when(Hungry::new(behavior.hunger), move_and_eat)
+            }
+            Diet::Carnivore => {
+                let hunt = Steps::build()
+                    .label("Hunt")
+                    .step(Stalk::new(self.perception_radius, behavior.pounce_distance))
+                    .step(Chase::new(
+                        behavior.kill_distance,
+                        behavior.give_up_distance,
+                    ))
+                    .step(Kill)
+                    .step(EatCarcass::new(behavior.eat_until, behavior.eat_per_second));
+
+                thinker
+                    .label("PredatorThinker")
+                    .when(Hungry::new(behavior.hunger), hunt)
+            }
+        }
+    }
+}
+
+impl AnimalType {
+    pub const ALL: [AnimalType; 2] = [AnimalType::Deer, AnimalType::Wolf];
+
+    pub fn species(&self) -> &'static SpeciesConfig {
+        match self {
+            AnimalType::Deer => &DEER,
+            AnimalType::Wolf => &WOLF,
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use avian3d::prelude::*;
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+use crate::bounds::{WorldBounds, WorldSeed};
+
+const TERRAIN_CONFIG: TerrainConfig = TerrainConfig {
+    cell_size: 1.,
+    height: 3.,
+    frequency: 0.04,
+    octaves: 4,
+    max_walkable_slope: 0.7,
+};
+
+/// Rolling hills from layered value noise, sampled on a grid that covers the whole
+/// ground, walkable area and margin both.
+#[derive(Resource, Clone, Debug)]
+pub struct Terrain {
+    half_extent: f32,
+    cell_size: f32,
+    /// Vertices along each side, one more than cells.
+    vertices_per_side: usize,
+    /// Indexed by `x * vertices_per_side + z`.
+    heights: Vec<f32>,
+}
+
+impl FromWorld for Terrain {
+    fn from_world(world: &mut World) -> Self {
+        let bounds = world.resource::<WorldBounds>();
+        let half_extent = 0.5 * bounds.ground_size();
+        let seed = world.resource::<WorldSeed>().0;
+        Terrain::generate(seed, half_extent, TERRAIN_CONFIG.cell_size)
+    }
+}
+
+impl Terrain {
+    pub fn generate(seed: u64, half_extent: f32, cell_size: f32) -> Self {
+        let cells = (2. * half_extent / cell_size).ceil() as usize;
+        let vertices_per_side = cells + 1;
+        let mut heights = Vec::with_capacity(vertices_per_side * vertices_per_side);
+        for x in 0..vertices_per_side {
+            for z in 0..vertices_per_side {
+                let point = Vec2::new(
+                    x as f32 * cell_size - half_extent,
+                    z as f32 * cell_size - half_extent,
+                );
+                let noise = fractal_noise(
+                    seed,
+                    point,
+                    TERRAIN_CONFIG.frequency,
+                    TERRAIN_CONFIG.octaves,
+                );
+                heights.push(TERRAIN_CONFIG.height * noise);
+            }
+        }
+        Self {
+            half_extent,
+            cell_size,
+            vertices_per_side,
+            heights,
+        }
+    }
+
+    /// Highest a hill gets, and deepest a valley.
+    pub fn max_height(&self) -> f32 {
+        TERRAIN_CONFIG.height
+    }
+
+    fn vertex_height(&self, x: usize, z: usize) -> f32 {
+        self.heights[x * self.vertices_per_side + z]
+    }
+
+    fn vertex_position(&self, x: usize, z: usize) -> Vec3 {
+        Vec3::new(
+            x as f32 * self.cell_size - self.half_extent,
+            self.vertex_height(x, z),
+            z as f32 * self.cell_size - self.half_extent,
+        )
+    }
+
+    /// Ground height under a point. Past the edge it carries on the edge height.
+    pub fn height_at(&self, position: Vec3) -> f32 {
+        let last = (self.vertices_per_side - 1) as f32;
+        let x = ((position.x + self.half_extent) / self.cell_size).clamp(0., last);
+        let z = ((position.z + self.half_extent) / self.cell_size).clamp(0., last);
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (x1, z1) = (
+            (x0 + 1).min(self.vertices_per_side - 1),
+            (z0 + 1).min(self.vertices_per_side - 1),
+        );
+        let (tx, tz) = (x.fract(), z.fract());
+
+        // Matches how the mesh splits each cell into two triangles.
+        let h00 = self.vertex_height(x0, z0);
+        let h10 = self.vertex_height(x1, z0);
+        let h01 = self.vertex_height(x0, z1);
+        let h11 = self.vertex_height(x1, z1);
+        if tx + tz <= 1. {
+            h00 + (h10 - h00) * tx + (h01 - h00) * tz
+        } else {
+            h11 + (h01 - h11) * (1. - tx) + (h10 - h11) * (1. - tz)
+        }
+    }
+
+    /// How steep the ground is at a point, as rise over run.
+    pub fn slope_at(&self, position: Vec3) -> f32 {
+        let step = 0.5 * self.cell_size;
+        let dx =
+            self.height_at(position + Vec3::X * step) - self.height_at(position - Vec3::X * step);
+        let dz =
+            self.height_at(position + Vec3::Z * step) - self.height_at(position - Vec3::Z * step);
+        Vec2::new(dx, dz).length() / (2. * step)
+    }
+
+    pub fn is_walkable(&self, position: Vec3) -> bool {
+        self.slope_at(position) <= TERRAIN_CONFIG.max_walkable_slope
+    }
+
+    /// Centers of the cells too steep to walk up, and the cell size.
+    pub fn steep_cells(&self) -> (Vec<Vec3>, f32) {
+        let cells = self.vertices_per_side - 1;
+        let steep = (0..cells)
+            .flat_map(|x| (0..cells).map(move |z| (x, z)))
+            .map(|(x, z)| {
+                let center =
+                    0.5 * (self.vertex_position(x, z) + self.vertex_position(x + 1, z + 1));
+                center.with_y(self.height_at(center))
+            })
+            .filter(|center| !self.is_walkable(*center))
+            .collect();
+        (steep, self.cell_size)
+    }
+
+    /// The ground mesh, with each vertex coloured by `color_at`.
+    pub fn mesh(&self, color_at: impl Fn(Vec3) -> Color) -> Mesh {
+        let n = self.vertices_per_side;
+        let mut positions = Vec::with_capacity(n * n);
+        let mut normals = Vec::with_capacity(n * n);
+        let mut uvs = Vec::with_capacity(n * n);
+        let mut colors = Vec::with_capacity(n * n);
+        for x in 0..n {
+            for z in 0..n {
+                let position = self.vertex_position(x, z);
+                let step = self.cell_size;
+                let dx = self.height_at(position + Vec3::X * step)
+                    - self.height_at(position - Vec3::X * step);
+                let dz = self.height_at(position + Vec3::Z * step)
+                    - self.height_at(position - Vec3::Z * step);
+                positions.push(position.to_array());
+                normals.push(Vec3::new(-dx, 2. * step, -dz).normalize().to_array());
+                uvs.push([x as f32 / (n - 1) as f32, z as f32 / (n - 1) as f32]);
+                colors.push(color_at(position).to_linear().to_f32_array());
+            }
+        }
+
+        let mut indices = Vec::with_capacity((n - 1) * (n - 1) * 6);
+        for x in 0..n - 1 {
+            for z in 0..n - 1 {
+                let a = (x * n + z) as u32;
+                let b = a + 1;
+                let c = a + n as u32;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, b, c, c, b, d]);
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+
+    pub fn collider(&self) -> Collider {
+        // Outer rows run along x, same as `heights`.
+        let heights = self
+            .heights
+            .chunks(self.vertices_per_side)
+            .map(|row| row.to_vec())
+            .collect();
+        let size = 2. * self.half_extent;
+        Collider::heightfield(heights, Vec3::new(size, 1., size))
+    }
+}
+
+/// Smoothly interpolated random values on a unit grid, between -1 and 1.
+fn value_noise(seed: u64, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let t = point - cell;
+    let t = t * t * (Vec2::splat(3.) - 2. * t);
+    let corner = |dx: i32, dz: i32| lattice_value(seed, cell.x as i32 + dx, cell.y as i32 + dz);
+
+    let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * t.x;
+    let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+fn lattice_value(seed: u64, x: i32, z: i32) -> f32 {
+    // SplitMix64 over the seed and coordinates.
+    let mut hash = seed
+        ^ (x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 24) as f32 * 2. - 1.
+}
+
+/// Octaves of `value_noise`, each twice as detailed and half as strong as the last,
+/// normalised back to between -1 and 1. `frequency` is that of the broadest octave,
+/// in waves per world unit.
+pub fn fractal_noise(seed: u64, point: Vec2, frequency: f32, octaves: u32) -> f32 {
+    let mut total = 0.;
+    let mut amplitude = 1.;
+    let mut frequency = frequency;
+    let mut max = 0.;
+    for octave in 0..octaves {
+        total += amplitude * value_noise(seed.wrapping_add(octave as u64), point * frequency);
+        max += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.;
+    }
+    total / max
+}
+
+struct TerrainConfig {
+    cell_size: f32,
+    /// Tallest a hill gets, and deepest a valley.
+    height: f32,
+    /// Of the broadest octave, in waves per world unit.
+    frequency: f32,
+    octaves: u32,
+    /// Steepest ground animals can walk, as rise over run.
+    max_walkable_slope: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_terrain() {
+        let a = Terrain::generate(7, 10., 1.);
+        let b = Terrain::generate(7, 10., 1.);
+        let c = Terrain::generate(8, 10., 1.);
+
+        assert_eq!(a.heights, b.heights);
+        assert_ne!(a.heights, c.heights);
+    }
+
+    #[test]
+    fn heights_stay_within_the_configured_range() {
+        let terrain = Terrain::generate(3, 20., 1.);
+
+        assert!(
+            terrain
+                .heights
+                .iter()
+                .all(|h| h.abs() <= TERRAIN_CONFIG.height)
+        );
+    }
+
+    #[test]
+    fn height_at_matches_the_grid_and_carries_on_past_the_edge() {
+        let terrain = Terrain::generate(11, 5., 1.);
+
+        assert_eq!(
+            terrain.height_at(Vec3::new(-5., 0., -5.)),
+            terrain.vertex_height(0, 0)
+        );
+        assert_eq!(
+            terrain.height_at(Vec3::new(-2., 0., 1.)),
+            terrain.vertex_height(3, 6)
+        );
+        assert_eq!(
+            terrain.height_at(Vec3::new(50., 0., -50.)),
+            terrain.vertex_height(10, 0)
+        );
+
+        let between = terrain.height_at(Vec3::new(-4.5, 0., -5.));
+        let expected = 0.5 * (terrain.vertex_height(0, 0) + terrain.vertex_height(1, 0));
+        assert!((between - expected).abs() < 1e-5);
+    }
+}
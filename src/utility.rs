@@ -0,0 +1,160 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use big_brain::{choices::Choice, pickers::Picker, scorers::Score};
+
+/// Maps a scorer's raw input, normalized to 0..1, onto the score it reports.
+#[derive(Clone, Copy, Debug)]
+pub enum ResponseCurve {
+    Linear {
+        slope: f32,
+        intercept: f32,
+    },
+    /// S-shaped curve centred on `midpoint`. Higher `steepness` gets closer to a step.
+    Logistic {
+        steepness: f32,
+        midpoint: f32,
+    },
+    /// `x^exponent`. Exponents above 1 stay low until `x` gets close to 1.
+    Exponential {
+        exponent: f32,
+    },
+    /// Straight lines between `(x, y)` points, which must be sorted by `x`.
+    Piecewise(&'static [(f32, f32)]),
+}
+
+impl ResponseCurve {
+    pub const IDENTITY: ResponseCurve = ResponseCurve::Linear {
+        slope: 1.,
+        intercept: 0.,
+    };
+
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let x = x.clamp(0., 1.);
+        let y = match *self {
+            ResponseCurve::Linear { slope, intercept } => slope * x + intercept,
+            ResponseCurve::Logistic {
+                steepness,
+                midpoint,
+            } => 1. / (1. + (-steepness * (x - midpoint)).exp()),
+            ResponseCurve::Exponential { exponent } => x.powf(exponent),
+            ResponseCurve::Piecewise(points) => piecewise(points, x),
+        };
+        y.clamp(0., 1.)
+    }
+}
+
+fn piecewise(points: &[(f32, f32)], x: f32) -> f32 {
+    let (Some(&(first_x, first_y)), Some(&(last_x, last_y))) = (points.first(), points.last())
+    else {
+        return 0.;
+    };
+    if x <= first_x {
+        return first_y;
+    }
+    if x >= last_x {
+        return last_y;
+    }
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            if x1 == x0 {
+                return y1;
+            }
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    last_y
+}
+
+/// Picks the highest scoring choice above `threshold`, but only switches away from
+/// the previously picked choice when another one beats it by more than `inertia`.
+/// That keeps animals from flip-flopping between two needs with similar scores.
+#[derive(Debug)]
+pub struct HighestWithInertia {
+    threshold: f32,
+    inertia: f32,
+    current: Mutex<Option<usize>>,
+}
+
+impl HighestWithInertia {
+    pub fn new(threshold: f32, inertia: f32) -> Self {
+        Self {
+            threshold,
+            inertia,
+            current: Mutex::new(None),
+        }
+    }
+}
+
+impl Picker for HighestWithInertia {
+    fn pick<'a>(&self, choices: &'a [Choice], scores: &Query<&Score>) -> Option<&'a Choice> {
+        let mut current = self.current.lock().unwrap();
+
+        let mut best: Option<(usize, f32)> = None;
+        for (index, choice) in choices.iter().enumerate() {
+            let mut score = choice.calculate(scores);
+            if score < self.threshold {
+                continue;
+            }
+            if *current == Some(index) {
+                score += self.inertia;
+            }
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((index, score));
+            }
+        }
+
+        *current = best.map(|(index, _)| index);
+        best.map(|(index, _)| &choices[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResponseCurve;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn identity_passes_through() {
+        assert!(close(ResponseCurve::IDENTITY.evaluate(0.3), 0.3));
+    }
+
+    #[test]
+    fn output_is_clamped() {
+        let curve = ResponseCurve::Linear {
+            slope: 2.,
+            intercept: 0.5,
+        };
+        assert!(close(curve.evaluate(0.9), 1.));
+        assert!(close(curve.evaluate(-3.), 0.5));
+    }
+
+    #[test]
+    fn logistic_is_half_at_midpoint() {
+        let curve = ResponseCurve::Logistic {
+            steepness: 10.,
+            midpoint: 0.7,
+        };
+        assert!(close(curve.evaluate(0.7), 0.5));
+        assert!(curve.evaluate(0.2) < 0.01);
+        assert!(curve.evaluate(1.) > 0.95);
+    }
+
+    #[test]
+    fn exponential() {
+        let curve = ResponseCurve::Exponential { exponent: 2. };
+        assert!(close(curve.evaluate(0.5), 0.25));
+    }
+
+    #[test]
+    fn piecewise_interpolates() {
+        let curve = ResponseCurve::Piecewise(&[(0.2, 0.), (0.6, 1.), (1., 1.)]);
+        assert!(close(curve.evaluate(0.), 0.));
+        assert!(close(curve.evaluate(0.4), 0.5));
+        assert!(close(curve.evaluate(0.8), 1.));
+    }
+}
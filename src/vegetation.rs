@@ -1,39 +1,135 @@
 use avian3d::prelude::*;
-use bevy::prelude::*;
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    scene::SceneInstanceReady,
+    utils::{HashMap, HashSet},
+};
 use rand::Rng;
-use std::ops::Range;
+use std::{f32::consts::TAU, ops::Range};
 
 use crate::{
-    age::Age, asset_loader::SceneAssets, navigation::Obstacle, schedule::StartupSet,
+    age::Age,
+    asset_loader::SceneAssets,
+    biome::{BiomeMap, BiomeWeights},
+    bounds::Bounded,
+    controller::{CharacterContactEvent, move_characters},
+    despawn::{DespawnCause, DespawnRequest},
+    life_cycle::{
+        DeathCause, DeathEvent, LifeCycle, LifeCycleConfig, LifeStage, MortalityCurve, PerStage,
+    },
+    navigation::{AgentSize, Obstacle},
+    needs::Attractiveness,
+    placement::{Placement, PlacementConstraints, PlacementError, PlacementKind, SearchArea},
+    schedule::StartupSet,
+    season::{Calendar, Wind},
+    soil::SoilGrid,
+    spatial::{SpatialIndex, SpatialIndexPlugin},
+    terrain::Terrain,
     time_control::TimeController,
 };
 
 const TREE_CONFIG: TreeConfig = TreeConfig {
-    spawn_range_x: -20.0..20.0,
-    spawn_range_z: -20.0..20.0,
-    grow_check_seconds: 0.5,
+    spawn_margin: 5.,
+    grow_check_sim_seconds: 60. * 60.,
     maturity_seconds: 60. * 60. * 24. * 5.,
-    lifespan_days: 300.,
-    min_dist_between_trees_sqrd: 2.8 * 2.8,
+    nutrients_to_mature: 0.5,
+    min_dist_between_trees: 2.8,
+    min_dist_from_seedling: 0.6,
+    trunk_radius: 0.2,
     initial_tree_count: 20,
     scale: 0.1,
+    fertility: PerStage {
+        newborn: 0.,
+        juvenile: 0.,
+        adult: 1.,
+        senescent: 0.5,
+    },
+};
+
+static TREE_LIFE_CYCLE: LifeCycleConfig = LifeCycleConfig {
+    juvenile_days: 1.,
+    adult_days: 5.,
+    senescent_days: 250.,
+    mortality: MortalityCurve {
+        onset_days: 250.,
+        initial_daily_hazard: 0.01,
+        doubling_days: 15.,
+    },
+};
+
+const SHADE_CONFIG: ShadeConfig = ShadeConfig {
+    check_sim_seconds: 60. * 60. * 6.,
+    canopy_radius: 2.,
+    growth_penalty: 0.9,
+    fruiting_penalty: 0.8,
+    sapling_max_grown: 0.5,
+    sapling_death_shade: 0.5,
+    sapling_daily_death_probability: 0.3,
 };
 
 const FRUIT_CONFIG: FruitConfig = FruitConfig {
     spawn_check_sim_seconds: 60. * 60. * 24.,
     decay_check_sim_seconds: 60. * 60. * 24.,
+    ripe_after_days: 3.,
+    overripe_after_days: 12.,
+    rotten_after_days: 22.,
     lifespan_days: 30.,
+    sprout_probability: 0.2,
     scale: 0.25,
     initial_fruit_count: 20,
     spawn_count_range: 1..4,
+    max_hanging: 8,
+    hang_height: 1.2..2.0,
+    hang_radius: 0.3..0.9,
+    drop_check_sim_seconds: 60. * 60.,
+    wind_drop_factor: 4.,
+    shake_drop_probability: 0.1,
+    placement_radius: 1.,
+    nutrients: 0.02,
 };
 
+const SPATIAL_CELL_SIZE: f32 = 4.0;
+
+/// At a fruiting rate of 1; the season scales it.
 const DAILY_FRUIT_PROBABILITY: f64 = 0.1;
-const TREE_SPAWN_FROM_FRUIT_PROBABILITY: f64 = 0.2;
 
 #[derive(Component, Debug)]
+#[require(TreeSpecies, Shade)]
 pub struct Tree;
 
+/// How much of a tree's canopy is shaded by its neighbours', from 0 to 1.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Shade(pub f32);
+
+/// They all share a model, but grow to different sizes and don't all fruit as much.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TreeSpecies {
+    #[default]
+    Oak,
+    Pine,
+    Willow,
+}
+
+impl TreeSpecies {
+    pub fn mature_scale(&self) -> f32 {
+        match self {
+            TreeSpecies::Oak => 1.0,
+            TreeSpecies::Pine => 1.2,
+            TreeSpecies::Willow => 0.9,
+        }
+    }
+
+    /// Multiplier on how often it fruits.
+    pub fn fruiting(&self) -> f64 {
+        match self {
+            TreeSpecies::Oak => 1.0,
+            TreeSpecies::Pine => 0.3,
+            TreeSpecies::Willow => 0.6,
+        }
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct SpawnTreeEvent {
     translation: Option<Vec3>,
@@ -46,16 +142,235 @@ impl SpawnTreeEvent {
 }
 
 #[derive(Component, Clone, Debug)]
+#[require(
+    Bounded,
+    Decay,
+    Ripeness,
+    Attractiveness(|| Attractiveness(Ripeness::Unripe.attractiveness()))
+)]
 pub struct Fruit;
 
-#[derive(Event, Debug)]
+/// Fruit still on its tree, as a child of it. It ripens up there, then drops to
+/// become a `Fruit` on the ground, unless something tall enough eats it first.
+#[derive(Component, Clone, Debug)]
+#[require(
+    Decay,
+    Ripeness,
+    Attractiveness(|| Attractiveness(Ripeness::Unripe.attractiveness()))
+)]
+pub struct HangingFruit {
+    /// Where it hangs from the foot of the tree, in the tree's frame but ignoring
+    /// its scale, so it stays put while the tree grows.
+    offset: Vec3,
+}
+
+/// How far along a fruit is, in days at the mean temperature. It ripens and rots
+/// faster in the warm and slower in the cold.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Decay(pub f32);
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Ripeness {
+    #[default]
+    Unripe,
+    Ripe,
+    Overripe,
+    Rotten,
+}
+
+impl Ripeness {
+    pub fn from_decay(days: f32) -> Self {
+        if days >= FRUIT_CONFIG.rotten_after_days {
+            Ripeness::Rotten
+        } else if days >= FRUIT_CONFIG.overripe_after_days {
+            Ripeness::Overripe
+        } else if days >= FRUIT_CONFIG.ripe_after_days {
+            Ripeness::Ripe
+        } else {
+            Ripeness::Unripe
+        }
+    }
+
+    /// Multiplier on how filling it is.
+    pub fn nutrition(&self) -> f32 {
+        match self {
+            Ripeness::Unripe => 0.4,
+            Ripeness::Ripe => 1.0,
+            Ripeness::Overripe => 0.7,
+            Ripeness::Rotten => 0.1,
+        }
+    }
+
+    /// How keen animals are to go for it. Nothing bothers with rotten fruit.
+    pub fn attractiveness(&self) -> f32 {
+        match self {
+            Ripeness::Unripe => 0.3,
+            Ripeness::Ripe => 1.0,
+            Ripeness::Overripe => 0.6,
+            Ripeness::Rotten => 0.,
+        }
+    }
+
+    /// Chance a day of it falling off the tree, on a still day.
+    fn daily_drop_probability(&self) -> f64 {
+        match self {
+            Ripeness::Unripe => 0.02,
+            Ripeness::Ripe => 0.1,
+            Ripeness::Overripe => 0.4,
+            Ripeness::Rotten => 1.0,
+        }
+    }
+
+    /// Multiplied into the model's colours.
+    fn tint(&self) -> Color {
+        match self {
+            Ripeness::Unripe => Color::srgb(0.55, 0.9, 0.45),
+            Ripeness::Ripe => Color::WHITE,
+            Ripeness::Overripe => Color::srgb(0.75, 0.55, 0.4),
+            Ripeness::Rotten => Color::srgb(0.35, 0.3, 0.25),
+        }
+    }
+}
+
+/// Tinted copies of the fruit model's materials, one per material and ripeness.
+#[derive(Resource, Debug, Default)]
+struct FruitTints(HashMap<(AssetId<StandardMaterial>, Ripeness), Handle<StandardMaterial>>);
+
+/// The material a fruit's mesh came with, before it was tinted.
+#[derive(Component, Debug)]
+struct UntintedMaterial(Handle<StandardMaterial>);
+
+#[derive(Event, Clone, Debug)]
 pub struct SpawnFruitEvent {
     translation: Option<Vec3>,
+    /// How far along it already is, as in `Decay`.
+    decay: f32,
 }
 
 impl SpawnFruitEvent {
-    fn new(translation: Option<Vec3>) -> Self {
-        Self { translation }
+    fn new(translation: Option<Vec3>, decay: f32) -> Self {
+        Self { translation, decay }
+    }
+}
+
+/// Finds fruit hanging from trees.
+#[derive(SystemParam)]
+pub struct Canopies<'w, 's> {
+    tree_index: Res<'w, SpatialIndex<Tree>>,
+    trees: Query<'w, 's, (&'static Transform, Option<&'static Children>), With<Tree>>,
+    hanging: Query<'w, 's, (&'static Parent, &'static HangingFruit, &'static Ripeness)>,
+}
+
+impl Canopies<'_, '_> {
+    /// The best fruit hanging no higher than `reach` off the ground, from trees within
+    /// `range` of `from`, weighing how good it is against how far away. Returns the
+    /// fruit and where its tree is.
+    pub fn reachable(&self, from: Vec3, reach: f32, range: f32) -> Option<(Entity, Vec3)> {
+        self.tree_index
+            .within_radius(from, range)
+            .into_iter()
+            .filter_map(|(tree, position)| Some((position, self.trees.get(tree).ok()?.1)))
+            .flat_map(|(position, children)| {
+                children
+                    .into_iter()
+                    .flat_map(|children| children.iter())
+                    .filter_map(move |fruit| {
+                        let (_, hanging, ripeness) = self.hanging.get(*fruit).ok()?;
+                        let height = hanging.offset.y;
+                        let attractiveness = ripeness.attractiveness();
+                        (height <= reach && attractiveness > 0.).then(|| {
+                            let weighted = from.distance(position) / attractiveness;
+                            (*fruit, position, weighted)
+                        })
+                    })
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(fruit, position, _)| (fruit, position))
+    }
+
+    /// Where a fruit's tree is and how ripe the fruit is, while it's still hanging.
+    pub fn hanging(&self, fruit: Entity) -> Option<(Vec3, Ripeness)> {
+        let (parent, _, ripeness) = self.hanging.get(fruit).ok()?;
+        let (tree_transform, _) = self.trees.get(parent.get()).ok()?;
+        Some((tree_transform.translation, *ripeness))
+    }
+}
+
+/// Hangs fruit on trees and takes it down again.
+#[derive(SystemParam)]
+pub struct Orchard<'w, 's> {
+    commands: Commands<'w, 's>,
+    scene_assets: Res<'w, SceneAssets>,
+    trees: Query<'w, 's, (&'static Transform, Option<&'static Children>), With<Tree>>,
+    hanging: Query<'w, 's, (&'static Parent, &'static HangingFruit, &'static Decay)>,
+    spawn_fruit_event_writer: EventWriter<'w, SpawnFruitEvent>,
+    despawn_request_writer: EventWriter<'w, DespawnRequest>,
+}
+
+impl Orchard<'_, '_> {
+    fn hanging_count(&self, tree: Entity) -> usize {
+        self.trees
+            .get(tree)
+            .ok()
+            .and_then(|(_, children)| children)
+            .map_or(0, |children| {
+                children
+                    .iter()
+                    .filter(|child| self.hanging.contains(**child))
+                    .count()
+            })
+    }
+
+    /// Hangs a new, unripe fruit somewhere in the tree's canopy.
+    fn hang(&mut self, tree: Entity, rng: &mut impl Rng) {
+        let Ok((transform, _)) = self.trees.get(tree) else {
+            return;
+        };
+        let direction = Vec2::from_angle(rng.random_range(0.0..TAU))
+            * rng.random_range(FRUIT_CONFIG.hang_radius);
+        // Sized to the tree as it is now, and kept there as it grows.
+        let offset = Vec3::new(
+            direction.x,
+            rng.random_range(FRUIT_CONFIG.hang_height),
+            direction.y,
+        ) * transform.scale;
+        let fruit = self
+            .commands
+            .spawn((
+                Name::new("Hanging fruit"),
+                SceneRoot(self.scene_assets.fruit.clone()),
+                hanging_transform(offset, transform.scale),
+                HangingFruit { offset },
+            ))
+            .observe(tint_when_loaded)
+            .id();
+        self.commands.entity(tree).add_child(fruit);
+    }
+
+    /// Swaps a hanging fruit for one on the ground below it.
+    fn knock_down(&mut self, fruit: Entity) {
+        let Ok((parent, hanging, decay)) = self.hanging.get(fruit) else {
+            return;
+        };
+        let Ok((tree, _)) = self.trees.get(parent.get()) else {
+            return;
+        };
+        let landing = (tree.translation + tree.rotation * hanging.offset).with_y(0.);
+        self.spawn_fruit_event_writer
+            .send(SpawnFruitEvent::new(Some(landing), decay.0));
+        self.take_down(fruit, DespawnCause::Fell);
+    }
+
+    /// Eats a fruit straight off its tree.
+    pub fn pick(&mut self, fruit: Entity) {
+        self.take_down(fruit, DespawnCause::Eaten);
+    }
+
+    fn take_down(&mut self, fruit: Entity, cause: DespawnCause) {
+        // See `DespawnRequest`.
+        self.commands.entity(fruit).remove::<HangingFruit>();
+        self.despawn_request_writer
+            .send(DespawnRequest::new(fruit, cause));
     }
 }
 
@@ -64,6 +379,11 @@ pub struct GrowTimer {
     timer: Timer,
 }
 
+#[derive(Resource, Debug)]
+pub struct ShadeTimer {
+    timer: Timer,
+}
+
 #[derive(Resource, Debug)]
 pub struct FruitTimer {
     timer: Timer,
@@ -74,12 +394,20 @@ pub struct DecayTimer {
     timer: Timer,
 }
 
+#[derive(Resource, Debug)]
+pub struct DropTimer {
+    timer: Timer,
+}
+
 pub struct VegetationPlugin;
 
 impl Plugin for VegetationPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GrowTimer {
-            timer: Timer::from_seconds(TREE_CONFIG.grow_check_seconds, TimerMode::Repeating),
+            timer: Timer::from_seconds(TREE_CONFIG.grow_check_sim_seconds, TimerMode::Repeating),
+        })
+        .insert_resource(ShadeTimer {
+            timer: Timer::from_seconds(SHADE_CONFIG.check_sim_seconds, TimerMode::Repeating),
         })
         .insert_resource(FruitTimer {
             timer: Timer::from_seconds(FRUIT_CONFIG.spawn_check_sim_seconds, TimerMode::Repeating),
@@ -87,20 +415,31 @@ impl Plugin for VegetationPlugin {
         .insert_resource(DecayTimer {
             timer: Timer::from_seconds(FRUIT_CONFIG.decay_check_sim_seconds, TimerMode::Repeating),
         })
+        .insert_resource(DropTimer {
+            timer: Timer::from_seconds(FRUIT_CONFIG.drop_check_sim_seconds, TimerMode::Repeating),
+        })
+        .init_resource::<FruitTints>()
+        .add_plugins(SpatialIndexPlugin::<Tree>::new(SPATIAL_CELL_SIZE))
+        .add_plugins(SpatialIndexPlugin::<Fruit>::new(SPATIAL_CELL_SIZE))
         .add_event::<SpawnTreeEvent>()
         .add_event::<SpawnFruitEvent>()
         .add_systems(Startup, spawn_fruits.in_set(StartupSet::StartupRoundB))
         .add_systems(Startup, spawn_trees.in_set(StartupSet::StartupRoundB))
-        .add_systems(FixedUpdate, grow)
+        .add_systems(FixedUpdate, (cast_shade, grow).chain())
         // .add_systems(FixedUpdate, spawn_trees)
-        .add_systems(FixedUpdate, (grow_fruit, spawn_fruit).chain())
-        .add_systems(FixedUpdate, (decay_fruit, spawn_tree).chain());
+        .add_systems(
+            FixedUpdate,
+            (grow_fruit, drop_fruit.after(move_characters), spawn_fruit).chain(),
+        )
+        .add_systems(FixedUpdate, (decay_fruit, spawn_tree).chain())
+        .add_systems(Update, tint_fruit);
     }
 }
 
 pub fn spawn_fruits(mut spawn_fruit_event_writer: EventWriter<SpawnFruitEvent>) {
     for _ in 0..FRUIT_CONFIG.initial_fruit_count {
-        spawn_fruit_event_writer.send(SpawnFruitEvent::new(None));
+        // What's lying around when the world starts is ready to eat.
+        spawn_fruit_event_writer.send(SpawnFruitEvent::new(None, FRUIT_CONFIG.ripe_after_days));
     }
 }
 
@@ -114,108 +453,209 @@ fn spawn_tree(
     mut commands: Commands,
     scene_assets: Res<SceneAssets>,
     time_controller: Res<TimeController>,
-    tree_query: Query<&Transform, With<Tree>>,
+    biome_map: Res<BiomeMap>,
+    terrain: Res<Terrain>,
+    mut placement: Placement,
     mut spawn_tree_event_reader: EventReader<SpawnTreeEvent>,
 ) {
+    let spaced_out = PlacementConstraints::new()
+        .clear_of_colliders()
+        .min_distance_from(PlacementKind::Tree, TREE_CONFIG.min_dist_between_trees)
+        .preferring(BiomeWeights::tree_density());
+    // Seedlings can come up in a crowd; shade thins them out later.
+    let seedling = PlacementConstraints::new()
+        .clear_of_colliders()
+        .min_distance_from(PlacementKind::Tree, TREE_CONFIG.min_dist_from_seedling)
+        .preferring(BiomeWeights::tree_density());
+
     let mut rng = rand::rng();
-    let mut spawned: Vec<Transform> = Vec::from_iter(tree_query.iter().cloned());
 
     for spawn_event in spawn_tree_event_reader.read() {
         let random_angle = rng.random_range(0.0..std::f32::consts::PI);
         let rotation: Quat = Quat::from_axis_angle(Vec3::new(0., 1., 0.), random_angle);
 
-        let translation = spawn_event.translation;
-        let translation = translation.unwrap_or(Vec3::new(
-            rng.random_range(TREE_CONFIG.spawn_range_x),
-            0.,
-            rng.random_range(TREE_CONFIG.spawn_range_z),
-        ));
+        // Seedlings only take root exactly where they fell.
+        let (area, constraints) = match spawn_event.translation {
+            Some(center) => (SearchArea::Around { center, radius: 0. }, &seedling),
+            None => (
+                SearchArea::Anywhere {
+                    margin: TREE_CONFIG.spawn_margin,
+                },
+                &spaced_out,
+            ),
+        };
+        let translation = match placement.place(TREE_CONFIG.trunk_radius, area, constraints) {
+            Ok(translation) => translation,
+            Err(err) => {
+                debug!(
+                    "Not spawning tree at {:?}: {}",
+                    spawn_event.translation, err
+                );
+                continue;
+            }
+        };
+
+        let species = biome_map
+            .biome_at(translation)
+            .config()
+            .pick_tree_species(rng.random());
 
         let collider_stuff = (
-            Collider::cylinder(0.2, 2.0),
+            Collider::cylinder(TREE_CONFIG.trunk_radius, 2.0),
             Transform::from_translation(Vec3::new(0.0, 1.0, 0.0)),
+        );
+        // The navmesh is cut where obstacles cross y=0, which a trunk on a hill or in
+        // a valley never does. This column reaches past any of them, even on the
+        // smallest tree, and isn't in any layer so nothing bumps into it.
+        let footprint = (
+            Name::new("Trunk footprint"),
+            Collider::cylinder(
+                TREE_CONFIG.trunk_radius,
+                2. * (terrain.max_height() + 1.) / TREE_CONFIG.scale,
+            ),
+            Transform::default(),
+            CollisionLayers::NONE,
             Obstacle,
         );
 
-        if !too_close_to_another_tree(spawned.iter(), &translation) {
-            let transform = Transform::from_translation(translation)
-                .with_rotation(rotation)
-                .with_scale(Vec3::splat(TREE_CONFIG.scale));
-            spawned.push(transform);
-            commands
-                .spawn((
-                    Name::new("Tree"),
-                    SceneRoot(scene_assets.tree.clone()),
-                    transform,
-                    Tree,
-                    Age::new(&time_controller),
-                ))
-                .with_children(|parent| {
-                    parent.spawn(collider_stuff);
-                });
-        }
+        let transform = Transform::from_translation(translation)
+            .with_rotation(rotation)
+            .with_scale(Vec3::splat(TREE_CONFIG.scale));
+        let tree = commands
+            .spawn((
+                Name::new("Tree"),
+                SceneRoot(scene_assets.tree.clone()),
+                transform,
+                Tree,
+                species,
+                Age::new(&time_controller),
+                LifeCycle::new(&TREE_LIFE_CYCLE),
+            ))
+            .with_children(|parent| {
+                parent.spawn(collider_stuff);
+                parent.spawn(footprint);
+            })
+            .observe(despawn_on_death)
+            .id();
+        placement.claim(PlacementKind::Tree, tree, translation);
     }
 }
 
+fn despawn_on_death(
+    trigger: Trigger<DeathEvent>,
+    mut despawn_request_writer: EventWriter<DespawnRequest>,
+) {
+    despawn_request_writer.send(DespawnRequest::new(
+        trigger.entity(),
+        DespawnCause::Died(trigger.event().cause),
+    ));
+}
+
 fn decay_fruit(
-    mut commands: Commands,
-    query: Query<(Entity, &Age, &Transform), With<Fruit>>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Decay,
+            &mut Ripeness,
+            &mut Attractiveness,
+            Has<HangingFruit>,
+        ),
+        Or<(With<Fruit>, With<HangingFruit>)>,
+    >,
     time_controller: Res<TimeController>,
+    calendar: Res<Calendar>,
+    mut soil_grid: ResMut<SoilGrid>,
     mut decay_timer: ResMut<DecayTimer>,
     mut spawn_tree_event_writer: EventWriter<SpawnTreeEvent>,
+    mut despawn_request_writer: EventWriter<DespawnRequest>,
 ) {
     decay_timer.timer.tick(time_controller.scaled_delta());
-    if !decay_timer.timer.just_finished() {
+    let checks = decay_timer.timer.times_finished_this_tick();
+    if checks == 0 {
         return;
     }
 
-    for (entity, age, transform) in query.iter() {
-        if age.age_days(&time_controller) as f32 > FRUIT_CONFIG.lifespan_days {
-            let mut rng = rand::rng();
-            if rng.random_bool(TREE_SPAWN_FROM_FRUIT_PROBABILITY) {
+    let mut rng = rand::rng();
+    let days = checks as f32 * FRUIT_CONFIG.decay_check_sim_seconds / (60. * 60. * 24.);
+    for (entity, transform, mut decay, mut ripeness, mut attractiveness, hanging) in
+        query.iter_mut()
+    {
+        decay.0 += days * calendar.decay_rate();
+        // Fruit still on a tree drops long before it's gone.
+        if !hanging && decay.0 > FRUIT_CONFIG.lifespan_days {
+            soil_grid.deposit(transform.translation, FRUIT_CONFIG.nutrients);
+            despawn_request_writer.send(DespawnRequest::new(entity, DespawnCause::Decayed));
+            continue;
+        }
+
+        let stage = Ripeness::from_decay(decay.0);
+        if stage != *ripeness {
+            // The seeds get their chance once the flesh has rotted away.
+            if stage == Ripeness::Rotten
+                && !hanging
+                && rng.random_bool(FRUIT_CONFIG.sprout_probability)
+            {
                 spawn_tree_event_writer.send(SpawnTreeEvent::new(Some(transform.translation)));
             }
-            // TODO: switch this to an event and then schedule despawns correctly
-            commands.entity(entity).despawn_recursive();
+            *ripeness = stage;
+            attractiveness.0 = stage.attractiveness();
         }
     }
 }
 
-fn too_close_to_another_tree<'a>(
-    iter: impl Iterator<Item = &'a Transform>,
-    translation: &Vec3,
-) -> bool {
-    match within_dist_sqrd_of_transforms(TREE_CONFIG.min_dist_between_trees_sqrd, iter, translation)
-    {
-        Some(_) => true,
-        None => false,
-    }
-}
-
-pub fn within_dist_sqrd_of_transforms<'a>(
-    dist_sqrd: f32,
-    iter: impl Iterator<Item = &'a Transform>,
-    translation: &Vec3,
-) -> Option<Vec3> {
-    let mut min_dist = 99999.0;
-    let mut min_dist_translation: Vec3 = Vec3::splat(0.0);
-    for transform in iter {
-        let cur_dist = transform.translation.distance_squared(translation.clone());
-        // println!(
-        //     "{:?} to {:?}: cur dist: {}",
-        //     transform.translation, translation, cur_dist
-        // );
-        if (cur_dist) < dist_sqrd {
-            if cur_dist < min_dist {
-                min_dist = cur_dist;
-                min_dist_translation = transform.translation;
-            }
+/// Tints each fruit's model for how ripe it is, whenever that changes.
+fn tint_fruit(
+    mut commands: Commands,
+    mut tints: ResMut<FruitTints>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    fruit: Query<(Entity, &Ripeness), (Or<(With<Fruit>, With<HangingFruit>)>, Changed<Ripeness>)>,
+    children: Query<&Children>,
+    mut meshes: Query<(
+        &mut MeshMaterial3d<StandardMaterial>,
+        Option<&UntintedMaterial>,
+    )>,
+) {
+    for (entity, ripeness) in fruit.iter() {
+        for child in children.iter_descendants(entity) {
+            let Ok((mut material, untinted)) = meshes.get_mut(child) else {
+                continue;
+            };
+            let untinted = match untinted {
+                Some(untinted) => untinted.0.clone(),
+                None => {
+                    commands
+                        .entity(child)
+                        .insert(UntintedMaterial(material.0.clone()));
+                    material.0.clone()
+                }
+            };
+            let tinted = tints
+                .0
+                .entry((untinted.id(), *ripeness))
+                .or_insert_with(|| {
+                    let mut tinted = materials.get(&untinted).cloned().unwrap_or_default();
+                    let base = tinted.base_color.to_linear();
+                    let tint = ripeness.tint().to_linear();
+                    tinted.base_color = LinearRgba::new(
+                        base.red * tint.red,
+                        base.green * tint.green,
+                        base.blue * tint.blue,
+                        base.alpha,
+                    )
+                    .into();
+                    materials.add(tinted)
+                })
+                .clone();
+            material.0 = tinted;
         }
     }
-    if min_dist < 99999.0 {
-        Some(min_dist_translation)
-    } else {
-        None
+}
+
+/// The model only turns up a little after the fruit, so tint it again then.
+fn tint_when_loaded(trigger: Trigger<SceneInstanceReady>, mut query: Query<&mut Ripeness>) {
+    if let Ok(mut ripeness) = query.get_mut(trigger.entity()) {
+        ripeness.set_changed();
     }
 }
 
@@ -223,111 +663,353 @@ fn spawn_fruit(
     mut commands: Commands,
     scene_assets: Res<SceneAssets>,
     time_controller: Res<TimeController>,
+    mut placement: Placement,
     // tree_query: Query<&Transform, With<Tree>>,
     mut spawn_fruit_event_reader: EventReader<SpawnFruitEvent>,
+    mut waiting_for_navmesh: Local<Vec<SpawnFruitEvent>>,
 ) {
-    let mut rng = rand::rng();
     // let mut spawned: Vec<Transform> = Vec::from_iter(tree_query.iter().cloned());
 
-    for spawn_event in spawn_fruit_event_reader.read() {
+    // Fruit has to be somewhere the smallest animals can get to.
+    let constraints = PlacementConstraints::new().on_navmesh(AgentSize::Small);
+    let spawn_events: Vec<SpawnFruitEvent> = waiting_for_navmesh
+        .drain(..)
+        .chain(spawn_fruit_event_reader.read().cloned())
+        .collect();
+
+    for spawn_event in spawn_events {
         // let random_angle = rng.random_range(0.0..std::f32::consts::PI);
         // let rotation: Quat = Quat::from_axis_angle(Vec3::new(0., 1., 0.), random_angle);
 
-        let translation = spawn_event.translation;
-        let translation = translation.unwrap_or(Vec3::new(
-            rng.random_range(TREE_CONFIG.spawn_range_x),
-            0.25 * FRUIT_CONFIG.scale,
-            rng.random_range(TREE_CONFIG.spawn_range_z),
-        ));
-
-        commands.spawn((
-            Name::new("Fruit"),
-            SceneRoot(scene_assets.fruit.clone()),
-            Transform::from_translation(translation).with_scale(Vec3::splat(FRUIT_CONFIG.scale)),
-            Fruit,
-            Age::new(&time_controller),
-        ));
+        let area = match spawn_event.translation {
+            Some(center) => SearchArea::Around {
+                center,
+                radius: FRUIT_CONFIG.placement_radius,
+            },
+            None => SearchArea::Anywhere {
+                margin: TREE_CONFIG.spawn_margin,
+            },
+        };
+        let translation = match placement.place(0.5 * FRUIT_CONFIG.scale, area, &constraints) {
+            Ok(translation) => translation + Vec3::Y * 0.25 * FRUIT_CONFIG.scale,
+            Err(PlacementError::NavMeshNotReady(_)) => {
+                waiting_for_navmesh.push(spawn_event);
+                continue;
+            }
+            Err(err) => {
+                debug!(
+                    "Not spawning fruit at {:?}: {}",
+                    spawn_event.translation, err
+                );
+                continue;
+            }
+        };
+
+        let stage = Ripeness::from_decay(spawn_event.decay);
+        let fruit = commands
+            .spawn((
+                Name::new("Fruit"),
+                SceneRoot(scene_assets.fruit.clone()),
+                Transform::from_translation(translation)
+                    .with_scale(Vec3::splat(FRUIT_CONFIG.scale)),
+                Fruit,
+                Decay(spawn_event.decay),
+                stage,
+                Attractiveness(stage.attractiveness()),
+                Age::new(&time_controller),
+            ))
+            .observe(tint_when_loaded)
+            .id();
+        placement.claim(PlacementKind::Fruit, fruit, translation);
     }
 }
 
 fn grow_fruit(
     time_controller: Res<TimeController>,
-    mut query: Query<(&Transform, &Age), With<Tree>>,
+    calendar: Res<Calendar>,
+    biome_map: Res<BiomeMap>,
+    mut soil_grid: ResMut<SoilGrid>,
+    query: Query<(Entity, &Transform, &LifeStage, &TreeSpecies, &Shade), With<Tree>>,
+    mut orchard: Orchard,
     mut fruit_timer: ResMut<FruitTimer>,
-    mut spawn_fruit_event_writer: EventWriter<SpawnFruitEvent>,
 ) {
     fruit_timer.timer.tick(time_controller.scaled_delta());
-    if !fruit_timer.timer.just_finished() {
+    let fruiting_rate = calendar.fruiting_rate();
+    if !fruit_timer.timer.just_finished() || fruiting_rate <= 0. {
         return;
     }
 
-    for (transform, age) in query.iter_mut() {
-        if age.age_seconds(&time_controller) as f32 >= TREE_CONFIG.maturity_seconds {
+    for (tree, transform, stage, species, shade) in query.iter() {
+        let fertility = TREE_CONFIG.fertility.get(*stage);
+        if fertility > 0. {
             let mut rng = rand::rng();
-            if rng.random_bool(DAILY_FRUIT_PROBABILITY) {
-                let fruit_count = rng.random_range(FRUIT_CONFIG.spawn_count_range);
+            let fruit_yield = species.fruiting()
+                * biome_map
+                    .biome_at(transform.translation)
+                    .config()
+                    .fruit_yield;
+            let shaded = 1. - (shade.0 * SHADE_CONFIG.fruiting_penalty) as f64;
+            let probability =
+                (DAILY_FRUIT_PROBABILITY * fruiting_rate * fertility * fruit_yield * shaded)
+                    .min(1.);
+            if rng.random_bool(probability) {
+                let room = FRUIT_CONFIG
+                    .max_hanging
+                    .saturating_sub(orchard.hanging_count(tree));
+                let fruit_count = rng
+                    .random_range(FRUIT_CONFIG.spawn_count_range)
+                    .min(room as u8);
+                // Poor soil can't feed as many.
+                let fed = soil_grid.draw(
+                    transform.translation,
+                    fruit_count as f32 * FRUIT_CONFIG.nutrients,
+                );
+                let fruit_count = (fruit_count as f32 * fed).round() as u8;
                 for _ in 0..fruit_count {
-                    let random_point = Vec3::new(
-                        rng.random_range(-100.0..100.0),
-                        0.,
-                        rng.random_range(-100.0..100.0),
-                    );
-                    let distance = rng.random_range((0.5 * FRUIT_CONFIG.scale)..4.0);
-                    let tree_point = transform.translation;
-                    let fruit_point = tree_point
-                        .move_towards(random_point, distance)
-                        .with_y(0.25 * FRUIT_CONFIG.scale);
-                    spawn_fruit_event_writer.send(SpawnFruitEvent::new(Some(fruit_point)));
+                    orchard.hang(tree, &mut rng);
                 }
             }
         }
     }
 }
 
+/// Local transform for fruit hanging at `offset` from a tree of the given scale,
+/// undoing the scale so the fruit comes out the usual size.
+fn hanging_transform(offset: Vec3, tree_scale: Vec3) -> Transform {
+    Transform::from_translation(offset / tree_scale)
+        .with_scale(Vec3::splat(FRUIT_CONFIG.scale) / tree_scale)
+}
+
+/// Knocks fruit off trees: now and then as it ripens, more often in the wind, and
+/// plenty whenever something bumps into the tree.
+fn drop_fruit(
+    time_controller: Res<TimeController>,
+    wind: Res<Wind>,
+    mut drop_timer: ResMut<DropTimer>,
+    parents: Query<&Parent>,
+    hanging: Query<(Entity, &Parent, &Ripeness), With<HangingFruit>>,
+    mut contact_event_reader: EventReader<CharacterContactEvent>,
+    mut orchard: Orchard,
+) {
+    // Trees' colliders are on their children.
+    let shaken: HashSet<Entity> = contact_event_reader
+        .read()
+        .filter_map(|contact| parents.get(contact.other).ok())
+        .map(|parent| parent.get())
+        .collect();
+
+    drop_timer.timer.tick(time_controller.scaled_delta());
+    let checks = drop_timer.timer.times_finished_this_tick();
+    if checks == 0 && shaken.is_empty() {
+        return;
+    }
+
+    let mut rng = rand::rng();
+    let days = (checks as f32 * FRUIT_CONFIG.drop_check_sim_seconds / (60. * 60. * 24.)) as f64;
+    let windiness = (1. + wind.strength() * FRUIT_CONFIG.wind_drop_factor) as f64;
+    for (fruit, parent, ripeness) in hanging.iter() {
+        let mut probability = ripeness.daily_drop_probability() * days * windiness;
+        if shaken.contains(&parent.get()) {
+            probability = probability.max(FRUIT_CONFIG.shake_drop_probability);
+        }
+        if rng.random_bool(probability.min(1.)) {
+            orchard.knock_down(fruit);
+        }
+    }
+}
+
 fn grow(
-    mut query: Query<(Entity, &mut Transform, &Age), With<Tree>>,
+    mut query: Query<(&mut Transform, &TreeSpecies, &Shade, Option<&Children>), With<Tree>>,
+    mut hanging: Query<(&HangingFruit, &mut Transform), Without<Tree>>,
     time_controller: Res<TimeController>,
+    mut soil_grid: ResMut<SoilGrid>,
     mut grow_timer: ResMut<GrowTimer>,
-    time: Res<Time>,
-    mut commands: Commands,
 ) {
-    grow_timer.timer.tick(time.delta());
-    if !grow_timer.timer.just_finished() {
+    grow_timer.timer.tick(time_controller.scaled_delta());
+    let checks = grow_timer.timer.times_finished_this_tick();
+    if checks == 0 {
         return;
     }
 
-    for (entity, mut transform, age) in query.iter_mut() {
-        if transform.scale.x < 1.0 {
-            let growth_pct =
-                age.age_seconds(&time_controller) as f32 / TREE_CONFIG.maturity_seconds;
-            let amount = 0.1 + (0.9 * growth_pct).min(0.9);
-            transform.scale = Vec3::splat(amount);
+    // Growing from a sapling to full size takes `maturity_seconds` on good soil.
+    let step =
+        0.9 * checks as f32 * TREE_CONFIG.grow_check_sim_seconds / TREE_CONFIG.maturity_seconds;
+    for (mut transform, species, shade, children) in query.iter_mut() {
+        let grown = transform.scale.x / species.mature_scale();
+        if grown < 1.0 {
+            let step = (step * (1. - shade.0 * SHADE_CONFIG.growth_penalty)).min(1.0 - grown);
+            let fed = soil_grid.draw(
+                transform.translation,
+                step / 0.9 * TREE_CONFIG.nutrients_to_mature,
+            );
+            transform.scale = Vec3::splat((grown + step * fed) * species.mature_scale());
+
+            // Fruit doesn't grow along with its tree.
+            for child in children.iter().flat_map(|children| children.iter()) {
+                if let Ok((fruit, mut fruit_transform)) = hanging.get_mut(*child) {
+                    *fruit_transform = hanging_transform(fruit.offset, transform.scale);
+                }
+            }
         }
-        if age.age_days(&time_controller) as f32 > TREE_CONFIG.lifespan_days {
-            // TODO: switch this to an event and then schedule despawns correctly
-            commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Works out how shaded every tree is, and lets the most shaded saplings die off.
+fn cast_shade(
+    mut commands: Commands,
+    time_controller: Res<TimeController>,
+    tree_index: Res<SpatialIndex<Tree>>,
+    mut shade_timer: ResMut<ShadeTimer>,
+    scales: Query<&Transform, With<Tree>>,
+    mut query: Query<(Entity, &Transform, &TreeSpecies, &mut Shade)>,
+) {
+    shade_timer.timer.tick(time_controller.scaled_delta());
+    if !shade_timer.timer.just_finished() {
+        return;
+    }
+
+    let mut rng = rand::rng();
+    let tallest = scales.iter().map(|t| t.scale.x).fold(0., f32::max);
+    let check_days = SHADE_CONFIG.check_sim_seconds / (60. * 60. * 24.);
+    for (entity, transform, species, mut shade) in query.iter_mut() {
+        let scale = transform.scale.x;
+        let reach = SHADE_CONFIG.canopy_radius * (scale + tallest);
+        shade.0 = tree_index
+            .within_radius(transform.translation, reach)
+            .into_iter()
+            .filter(|(other, _)| *other != entity)
+            .filter_map(|(other, position)| {
+                let other_scale = scales.get(other).ok()?.scale.x;
+                let distance = transform.translation.xz().distance(position.xz());
+                Some(shade_from(scale, other_scale, distance))
+            })
+            .sum::<f32>()
+            .min(1.);
+
+        let sapling = scale / species.mature_scale() < SHADE_CONFIG.sapling_max_grown;
+        if sapling
+            && shade.0 >= SHADE_CONFIG.sapling_death_shade
+            && rng.random_bool(
+                (SHADE_CONFIG.sapling_daily_death_probability * check_days).min(1.) as f64,
+            )
+        {
+            commands.trigger_targets(
+                DeathEvent {
+                    cause: DeathCause::Shade,
+                },
+                entity,
+            );
         }
-        // println!("Tree age: {}", age.formatted_age_string(&time_controller));
-        // println!("Tree age: {}s", age.age_seconds(&time_controller));
     }
 }
 
+/// How much a neighbour of `other_scale`, `distance` away, shades a tree of `scale`.
+/// Canopies only shade where they overlap, and smaller neighbours matter less.
+fn shade_from(scale: f32, other_scale: f32, distance: f32) -> f32 {
+    let reach = SHADE_CONFIG.canopy_radius * (scale + other_scale);
+    if reach <= 0. {
+        return 0.;
+    }
+    let overlap = (1. - distance / reach).max(0.);
+    overlap * (other_scale / scale).min(1.)
+}
+
 struct TreeConfig {
     initial_tree_count: u32,
-    spawn_range_x: Range<f32>,
-    spawn_range_z: Range<f32>,
-    grow_check_seconds: f32,
+    /// How far in from the edge of the world random trees and fruit are placed.
+    spawn_margin: f32,
+    grow_check_sim_seconds: f32,
     maturity_seconds: f32,
-    lifespan_days: f32,
-    min_dist_between_trees_sqrd: f32,
+    /// Drawn from the soil over the whole of growing up.
+    nutrients_to_mature: f32,
+    min_dist_between_trees: f32,
+    /// How close to another tree's trunk a seedling can come up.
+    min_dist_from_seedling: f32,
+    trunk_radius: f32,
     scale: f32,
+    /// How much a tree fruits at each stage of its life.
+    fertility: PerStage<f64>,
+}
+
+struct ShadeConfig {
+    check_sim_seconds: f32,
+    /// Canopy radius of a tree at scale 1. It grows with the tree.
+    canopy_radius: f32,
+    /// How much fully shaded trees are held back, from 0 to 1.
+    growth_penalty: f32,
+    fruiting_penalty: f32,
+    /// Trees smaller than this fraction of their full size are saplings.
+    sapling_max_grown: f32,
+    /// Saplings at least this shaded might die.
+    sapling_death_shade: f32,
+    sapling_daily_death_probability: f32,
 }
 
 struct FruitConfig {
     initial_fruit_count: u32,
     spawn_check_sim_seconds: f32,
     decay_check_sim_seconds: f32,
+    /// Days, at the mean temperature, to each stage of ripeness, and until it's gone.
+    ripe_after_days: f32,
+    overripe_after_days: f32,
+    rotten_after_days: f32,
     lifespan_days: f32,
+    /// Chance a fruit's seed sprouts once it's rotten.
+    sprout_probability: f64,
     scale: f32,
     spawn_count_range: Range<u8>,
+    /// Most fruit a tree carries at once.
+    max_hanging: usize,
+    /// Where on the tree fruit hangs, before the tree's scale: how high, and how far
+    /// out from the trunk.
+    hang_height: Range<f32>,
+    hang_radius: Range<f32>,
+    drop_check_sim_seconds: f32,
+    /// How many times more fruit drops in a gale than on a still day, less one.
+    wind_drop_factor: f32,
+    /// Chance each fruit falls for every tick something is pushing against its tree.
+    shake_drop_probability: f64,
+    /// How far from where it was meant to land a fruit can end up, if that spot's no good.
+    placement_radius: f32,
+    /// Drawn from the soil to grow each fruit, and given back when it rots.
+    nutrients: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fruit_ripens_then_rots() {
+        assert_eq!(Ripeness::from_decay(0.), Ripeness::Unripe);
+        assert_eq!(
+            Ripeness::from_decay(FRUIT_CONFIG.ripe_after_days),
+            Ripeness::Ripe
+        );
+        assert_eq!(
+            Ripeness::from_decay(FRUIT_CONFIG.rotten_after_days - 0.1),
+            Ripeness::Overripe
+        );
+        assert_eq!(
+            Ripeness::from_decay(FRUIT_CONFIG.lifespan_days),
+            Ripeness::Rotten
+        );
+        assert_eq!(Ripeness::Rotten.attractiveness(), 0.);
+    }
+
+    #[test]
+    fn canopies_only_shade_where_they_overlap() {
+        let reach = SHADE_CONFIG.canopy_radius * 2.;
+        assert_eq!(shade_from(1., 1., reach), 0.);
+        assert_eq!(shade_from(1., 1., 2. * reach), 0.);
+        assert!((shade_from(1., 1., 0.5 * reach) - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn saplings_are_shaded_more_than_what_shades_them() {
+        let under_canopy = shade_from(0.1, 1., 1.);
+        let over_sapling = shade_from(1., 0.1, 1.);
+        assert!(under_canopy > 0.4, "{under_canopy}");
+        assert!(over_sapling < 0.5 * under_canopy, "{over_sapling}");
+    }
 }